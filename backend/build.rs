@@ -7,6 +7,8 @@ fn main() {
         .unwrap()
         .collect::<Result<Vec<_>, _>>()
         .unwrap()
+        .into_iter()
+        .filter(|entry| entry.path().is_file())
     {
         let contract_name = entry
             .path()
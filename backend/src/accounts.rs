@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet};
+use web3::types::{H160, U256, U64};
+
+#[derive(Debug, Clone, Copy)]
+struct Reserved {
+    /// Most the transaction can spend on gas.
+    cost: U256,
+    /// Block the transaction was last sent at, if it was.
+    sent: Option<U64>,
+}
+
+/// An executor account as last seen on chain, along with the nonces handed
+/// out since then.
+#[derive(Debug, Clone, Default)]
+struct Account {
+    nonce: U256,
+    balance: U256,
+    reserved: BTreeMap<U256, Reserved>,
+    /// Nonces above the chain's that were handed out but never used.
+    free: BTreeSet<U256>,
+}
+
+impl Account {
+    fn available(&self) -> U256 {
+        self.reserved
+            .values()
+            .fold(self.balance, |balance, r| balance.saturating_sub(r.cost))
+    }
+
+    fn next_nonce(&mut self) -> U256 {
+        if let Some(&nonce) = self.free.iter().next() {
+            self.free.remove(&nonce);
+            return nonce;
+        }
+
+        match self.reserved.keys().next_back() {
+            Some(nonce) => (nonce + 1).max(self.nonce),
+            None => self.nonce,
+        }
+    }
+}
+
+/// Executor accounts along with a local view of their nonces and balances, so
+/// that executions can be sent in parallel and retried without colliding.
+#[derive(Debug, Clone)]
+pub struct AccountPool {
+    accounts: Vec<(H160, Account)>,
+    ttl: u64,
+}
+
+impl AccountPool {
+    /// Sent transactions are taken as dropped when they aren't mined within
+    /// `ttl` blocks, and their nonces handed out again.
+    pub fn new(addresses: impl IntoIterator<Item = H160>, ttl: u64) -> AccountPool {
+        AccountPool {
+            accounts: addresses
+                .into_iter()
+                .map(|address| (address, Account::default()))
+                .collect(),
+            ttl,
+        }
+    }
+
+    pub fn addresses(&self) -> Vec<H160> {
+        self.accounts.iter().map(|(address, _)| *address).collect()
+    }
+
+    fn account(&mut self, address: H160) -> Option<&mut Account> {
+        self.accounts
+            .iter_mut()
+            .find(|(a, _)| *a == address)
+            .map(|(_, account)| account)
+    }
+
+    /// Updates the account to how it is at the given block, forgetting the
+    /// mined nonces and freeing the dropped ones, which are returned.
+    pub fn sync(&mut self, address: H160, number: U64, nonce: U256, balance: U256) -> Vec<U256> {
+        let ttl = self.ttl;
+        let account = match self.account(address) {
+            Some(account) => account,
+            None => return vec![],
+        };
+
+        account.nonce = nonce;
+        account.balance = balance;
+        account.reserved = account.reserved.split_off(&nonce);
+        account.free = account.free.split_off(&nonce);
+
+        let dropped: Vec<_> = account
+            .reserved
+            .iter()
+            .filter(|(_, r)| r.sent.is_some_and(|sent| sent + ttl <= number))
+            .map(|(nonce, _)| *nonce)
+            .collect();
+
+        for nonce in &dropped {
+            account.reserved.remove(nonce);
+            account.free.insert(*nonce);
+        }
+
+        dropped
+    }
+
+    /// The most any account has left after what its pending transactions
+    /// may spend.
+    pub fn max_available(&self) -> U256 {
+        self.accounts
+            .iter()
+            .map(|(_, account)| account.available())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The least busy account that can pay the cost.
+    pub fn pick(&self, cost: U256) -> Option<H160> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| account.available() >= cost)
+            .min_by_key(|(_, account)| {
+                (
+                    account.reserved.len(),
+                    std::cmp::Reverse(account.available()),
+                )
+            })
+            .map(|(address, _)| *address)
+    }
+
+    /// Hands out a nonce of the account, if it can still pay the cost. Nonces
+    /// should only be reserved right before sending, as every one released
+    /// below a sent one holds it back until it is handed out again.
+    pub fn reserve(&mut self, address: H160, cost: U256) -> Option<U256> {
+        let account = self
+            .account(address)
+            .filter(|account| account.available() >= cost)?;
+
+        let nonce = account.next_nonce();
+        account
+            .reserved
+            .insert(nonce, Reserved { cost, sent: None });
+        Some(nonce)
+    }
+
+    /// Marks the nonce as used by a transaction sent at the given block.
+    pub fn sent(&mut self, address: H160, nonce: U256, number: U64) {
+        if let Some(reserved) = self
+            .account(address)
+            .and_then(|account| account.reserved.get_mut(&nonce))
+        {
+            reserved.sent = Some(number);
+        }
+    }
+
+    /// Gives back a nonce that no transaction went out with.
+    pub fn release(&mut self, address: H160, nonce: U256) {
+        if let Some(account) = self.account(address) {
+            if account.reserved.remove(&nonce).is_some() && nonce >= account.nonce {
+                account.free.insert(nonce);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reserve(pool: &mut AccountPool, cost: u64) -> Option<(H160, U256)> {
+        let address = pool.pick(cost.into())?;
+        let nonce = pool.reserve(address, cost.into())?;
+        Some((address, nonce))
+    }
+
+    #[test]
+    fn hands_out_nonces_without_collisions() {
+        let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let mut pool = AccountPool::new(vec![a, b], 2);
+        pool.sync(a, 1.into(), 5.into(), 100.into());
+        pool.sync(b, 1.into(), 7.into(), 50.into());
+        assert_eq!(pool.max_available(), 100.into());
+
+        assert_eq!(reserve(&mut pool, 40), Some((a, 5.into())));
+        assert_eq!(reserve(&mut pool, 40), Some((b, 7.into())));
+        assert_eq!(reserve(&mut pool, 40), Some((a, 6.into())));
+        assert_eq!(reserve(&mut pool, 40), None);
+        assert_eq!(pool.reserve(b, 40.into()), None);
+        assert_eq!(pool.max_available(), 20.into());
+
+        pool.sent(a, 5.into(), 1.into());
+        pool.release(a, 6.into());
+        pool.release(b, 7.into());
+        assert_eq!(reserve(&mut pool, 10), Some((b, 7.into())));
+        assert_eq!(reserve(&mut pool, 10), Some((a, 6.into())));
+
+        // Nonce 5 gets mined, while 6 is taken as dropped after two blocks.
+        pool.sent(a, 6.into(), 2.into());
+        assert!(pool.sync(a, 3.into(), 6.into(), 90.into()).is_empty());
+        assert_eq!(pool.sync(a, 4.into(), 6.into(), 90.into()), vec![6.into()]);
+        assert_eq!(reserve(&mut pool, 80), Some((a, 6.into())));
+    }
+
+    #[test]
+    fn fills_gaps_below_sent_nonces() {
+        let a = H160::from_low_u64_be(1);
+        let mut pool = AccountPool::new(vec![a], 2);
+        pool.sync(a, 1.into(), 5.into(), 100.into());
+
+        assert_eq!(pool.reserve(a, 10.into()), Some(5.into()));
+        assert_eq!(pool.reserve(a, 10.into()), Some(6.into()));
+
+        // 6 goes out while 5 doesn't, so 6 can't be mined until 5 is used.
+        pool.sent(a, 6.into(), 1.into());
+        pool.release(a, 5.into());
+        assert_eq!(pool.reserve(a, 10.into()), Some(5.into()));
+        assert_eq!(pool.reserve(a, 10.into()), Some(7.into()));
+
+        // Without 5 going out either, 6 is dropped and both are handed out again.
+        pool.release(a, 5.into());
+        pool.release(a, 7.into());
+        assert!(pool.sync(a, 2.into(), 5.into(), 100.into()).is_empty());
+        assert_eq!(pool.sync(a, 3.into(), 5.into(), 100.into()), vec![6.into()]);
+        assert_eq!(pool.reserve(a, 10.into()), Some(5.into()));
+        assert_eq!(pool.reserve(a, 10.into()), Some(6.into()));
+        assert_eq!(pool.reserve(a, 10.into()), Some(7.into()));
+    }
+}
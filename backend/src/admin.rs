@@ -0,0 +1,351 @@
+use crate::{journal::Entry, ledger};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use web3::types::{H160, H256, U256};
+
+const RECENT_CONFLICTS: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasScales {
+    pub min: u8,
+    pub max: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PairId {
+    pub uniswap_pair: H160,
+    pub balancer_pool: H160,
+}
+
+/// An execution in flight, along with the gas prices it was sent at so far.
+#[derive(Serialize, Debug, Clone)]
+pub struct Execution {
+    pub attempt: Entry,
+    pub executor: H160,
+    pub gas_prices: Vec<U256>,
+}
+
+/// A pending transaction competing with an execution in flight.
+#[derive(Serialize, Debug, Clone)]
+pub struct Conflict {
+    pub pair: PairId,
+    pub hash: H256,
+    pub gas_price: U256,
+    pub competitor: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Status {
+    pub paused: bool,
+    pub gas_scales: GasScales,
+    pub disabled_pairs: BTreeSet<PairId>,
+    /// Block the opportunities were found at.
+    pub block_number: Option<u64>,
+    /// Best attempts of the block, net profitable first.
+    pub opportunities: Vec<Entry>,
+    pub executions: Vec<Execution>,
+    /// Latest conflicts, most recent last.
+    pub conflicts: VecDeque<Conflict>,
+    /// Profits and losses of the day by profit token.
+    pub pnl: BTreeMap<H160, ledger::Entry>,
+}
+
+/// What watch_pairs is told to do and what it reports doing, shared with the
+/// admin API.
+#[derive(Clone)]
+pub struct Control {
+    status: Arc<Mutex<Status>>,
+}
+
+impl Control {
+    pub fn new(gas_scales: GasScales) -> Control {
+        Control {
+            status: Arc::new(Mutex::new(Status {
+                paused: false,
+                gas_scales,
+                disabled_pairs: BTreeSet::new(),
+                block_number: None,
+                opportunities: vec![],
+                executions: vec![],
+                conflicts: VecDeque::new(),
+                pnl: BTreeMap::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Status> {
+        self.status.lock().expect("control lock poisoned")
+    }
+
+    pub fn status(&self) -> Status {
+        self.lock().clone()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.lock().paused = paused;
+    }
+
+    pub fn enabled(&self, pair: PairId) -> bool {
+        !self.lock().disabled_pairs.contains(&pair)
+    }
+
+    pub fn set_enabled(&self, pair: PairId, enabled: bool) {
+        let mut status = self.lock();
+        if enabled {
+            status.disabled_pairs.remove(&pair);
+        } else {
+            status.disabled_pairs.insert(pair);
+        }
+    }
+
+    pub fn gas_scales(&self) -> GasScales {
+        self.lock().gas_scales
+    }
+
+    pub fn set_gas_scales(&self, gas_scales: GasScales) -> Result<(), String> {
+        if gas_scales.min == 0 || gas_scales.min > gas_scales.max {
+            return Err(format!(
+                "gas scales must be 0 < min <= max, got {} and {}",
+                gas_scales.min, gas_scales.max
+            ));
+        }
+
+        self.lock().gas_scales = gas_scales;
+        Ok(())
+    }
+
+    pub fn set_opportunities(&self, block_number: u64, opportunities: Vec<Entry>) {
+        let mut status = self.lock();
+        status.block_number = Some(block_number);
+        status.opportunities = opportunities;
+    }
+
+    pub fn start_execution(&self, attempt: Entry, executor: H160) {
+        self.lock().executions.push(Execution {
+            attempt,
+            executor,
+            gas_prices: vec![],
+        });
+    }
+
+    /// Notes the execution of the pair was sent at the gas price.
+    pub fn sent(&self, pair: PairId, gas_price: U256) {
+        if let Some(execution) = self.lock().executions.iter_mut().find(|e| e.pair() == pair) {
+            execution.gas_prices.push(gas_price);
+        }
+    }
+
+    pub fn finish_execution(&self, pair: PairId) {
+        self.lock().executions.retain(|e| e.pair() != pair);
+    }
+
+    pub fn conflict(&self, conflict: Conflict) {
+        let mut status = self.lock();
+        if status.conflicts.len() == RECENT_CONFLICTS {
+            status.conflicts.pop_front();
+        }
+        status.conflicts.push_back(conflict);
+    }
+
+    pub fn set_pnl(&self, pnl: BTreeMap<H160, ledger::Entry>) {
+        self.lock().pnl = pnl;
+    }
+}
+
+impl Execution {
+    fn pair(&self) -> PairId {
+        PairId {
+            uniswap_pair: self.attempt.uniswap_pair,
+            balancer_pool: self.attempt.balancer_pool,
+        }
+    }
+}
+
+fn json(status: StatusCode, value: impl Serialize) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::to_string(&value).expect("failed serializing response"),
+        ))
+        .expect("failed building response")
+}
+
+fn error(status: StatusCode, message: impl ToString) -> Response<Body> {
+    json(status, serde_json::json!({ "error": message.to_string() }))
+}
+
+async fn body<T: for<'de> Deserialize<'de>>(request: Request<Body>) -> Result<T, Response<Body>> {
+    let bytes = hyper::body::to_bytes(request.into_body())
+        .await
+        .map_err(|err| error(StatusCode::BAD_REQUEST, err))?;
+    serde_json::from_slice(&bytes).map_err(|err| error(StatusCode::BAD_REQUEST, err))
+}
+
+/// Whether the request says its body is json, which browsers can't send
+/// across origins without asking first.
+fn is_json(request: &Request<Body>) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+async fn respond(control: Control, request: Request<Body>) -> Response<Body> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+
+    if method == Method::POST && !is_json(&request) {
+        return error(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "content type must be application/json",
+        );
+    }
+
+    let result = match (&method, path.as_str()) {
+        (&Method::GET, "/status") => Ok(json(StatusCode::OK, control.status())),
+        (&Method::GET, "/opportunities") => {
+            Ok(json(StatusCode::OK, control.status().opportunities))
+        }
+        (&Method::GET, "/executions") => Ok(json(StatusCode::OK, control.status().executions)),
+        (&Method::POST, "/pause") => {
+            control.set_paused(true);
+            Ok(json(StatusCode::OK, control.status()))
+        }
+        (&Method::POST, "/resume") => {
+            control.set_paused(false);
+            Ok(json(StatusCode::OK, control.status()))
+        }
+        (&Method::POST, path @ "/pairs/enable") | (&Method::POST, path @ "/pairs/disable") => {
+            body(request).await.map(|pair| {
+                control.set_enabled(pair, path == "/pairs/enable");
+                json(StatusCode::OK, control.status())
+            })
+        }
+        (&Method::POST, "/gas_scales") => {
+            body(request)
+                .await
+                .map(|gas_scales| match control.set_gas_scales(gas_scales) {
+                    Ok(()) => json(StatusCode::OK, control.status()),
+                    Err(err) => error(StatusCode::BAD_REQUEST, err),
+                })
+        }
+        _ => Err(error(StatusCode::NOT_FOUND, "no such route")),
+    };
+
+    result.unwrap_or_else(|response| response)
+}
+
+/// Serves the admin API in the background, returning the address bound to.
+/// Nothing is authenticated, so it should only be bound locally.
+pub fn serve(address: &SocketAddr, control: Control) -> Result<SocketAddr, hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let control = control.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let control = control.clone();
+                async move { Ok::<_, Infallible>(respond(control, request).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(address)?.serve(make_service);
+    let bound = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            log::error!("Admin server failed: {}", err);
+        }
+    });
+
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn controls_the_bot() {
+        let control = Control::new(GasScales { min: 2, max: 5 });
+        let address = serve(&([127, 0, 0, 1], 0).into(), control.clone()).unwrap();
+        let url = |path| format!("http://{}{}", address, path);
+        let client = reqwest::Client::new();
+
+        let post = |path, body: Value| {
+            client
+                .post(&url(path))
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_string())
+                .send()
+        };
+
+        let form = client
+            .post(&url("/pause"))
+            .header(CONTENT_TYPE, "text/plain")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(form.status(), reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(!control.paused());
+
+        post("/pause", Value::Null).await.unwrap();
+        assert!(control.paused());
+
+        let pair = PairId {
+            uniswap_pair: H160::from_low_u64_be(1),
+            balancer_pool: H160::from_low_u64_be(2),
+        };
+        post("/pairs/disable", serde_json::to_value(pair).unwrap())
+            .await
+            .unwrap();
+        assert!(!control.enabled(pair));
+
+        let rejected = post("/gas_scales", json!({ "min": 6, "max": 5 }))
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), reqwest::StatusCode::BAD_REQUEST);
+        post("/gas_scales", json!({ "min": 3, "max": 6 }))
+            .await
+            .unwrap();
+        assert_eq!(control.gas_scales(), GasScales { min: 3, max: 6 });
+
+        let status: Value = client
+            .get(&url("/status"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(status["paused"], json!(true));
+        assert_eq!(status["disabled_pairs"].as_array().unwrap().len(), 1);
+
+        post("/resume", Value::Null).await.unwrap();
+        post("/pairs/enable", serde_json::to_value(pair).unwrap())
+            .await
+            .unwrap();
+        assert!(!control.paused());
+        assert!(control.enabled(pair));
+
+        let missing = client.get(&url("/nope")).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+}
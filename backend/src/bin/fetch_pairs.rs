@@ -1,39 +1,11 @@
 use bigdecimal::{BigDecimal, BigDecimal as BigInt, ToPrimitive};
 use futures::{Future, TryFutureExt};
 use graphql_client::{GraphQLQuery, Response};
-use pooller::{Pair, Pairs, Token};
+use pooller::{network::Networks, Pair, Pairs, Token};
 use reqwest::Client;
 use std::{collections::HashMap, collections::HashSet, fmt::Debug, str::FromStr, time::Duration};
 use tokio::time::delay_for;
-use web3::types::H160;
-
-const UNISWAP_URL: &str = "https://api.thegraph.com/subgraphs/name/ianlapham/uniswapv2";
-const BALANCER_URL: &str = "https://api.thegraph.com/subgraphs/name/balancer-labs/balancer-beta";
-const WETH_ADDRESS: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
-
-const ALLOWED_TOKENS: [&str; 21] = [
-    "C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", // WETH
-    "514910771AF9Ca656af840dff83E8264EcF986CA", // LINK
-    "A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", // USDC
-    "2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599", // WBTC
-    "6B175474E89094C44Da98b954EedeAC495271d0F", // DAI
-    "EA86074fdAC85E6a605cd418668C63d2716cdfBc", // AAVE
-    "0bc529c00C6401aEF6D220BE8C6Ea1667F6Ad93e", // YFI
-    "1f9840a85d5aF5bf1D1762F925BDADdC4201F984", // UNI
-    "5864c777697Bf9881220328BF2f16908c9aFCD7e", // BUSD
-    "C011a73ee8576Fb46F5E1c5751cA3B9Fe0af2a6F", // SNX
-    "9f8F72aA9304c8B593d555F12eF6589cC3A579A2", // MKR
-    "04Fa0d235C4abf4BcF4787aF4CF447DE572eF828", // UMA
-    "c00e94Cb662C3520282E6f5717214004A7f26888", // COMP
-    "0000000000085d4780B73119b644AE5ecd22b376", // TUSD
-    "408e41876cCCDC0F92210600ef50372656052a38", // REN
-    "BBbbCA6A901c926F240b89EacB641d8Aec7AEafD", // LRC
-    "6B3595068778DD592e39A122f4f5a5cF09C90fE2", // SUSHI
-    "dd974D5C2e2928deA5F71b9825b8b646686BD200", // KNC
-    "967da4048cD07aB37855c090aAF366e4ce1b9F48", // OCEAN
-    "ba100000625a3754423978a60c9317c58a424e3D", // BAL
-    "0F5D2fB29fb7d3CFeE444a200298f468908cC942", // MANA
-];
+use web3::{transports::Ipc, types::H160, Web3};
 
 #[derive(GraphQLQuery)]
 #[graphql(
@@ -80,6 +52,7 @@ async fn send<T, E: Debug, Fut: Future<Output = Result<Response<T>, E>>>(
 
 async fn uniswap_pairs(
     client: &Client,
+    url: &str,
     weth_address: H160,
     allowed_tokens: &[H160],
 ) -> Vec<(H160, Token, Token)> {
@@ -99,16 +72,10 @@ async fn uniswap_pairs(
             skip: 1000 * page,
         });
 
-        let data: uniswap_get_pairs::ResponseData = send(&|| {
-            client
-                .post(UNISWAP_URL)
-                .json(&query)
-                .send()
-                .and_then(|a| a.json())
-        })
-        .await;
+        let data: uniswap_get_pairs::ResponseData =
+            send(&|| client.post(url).json(&query).send().and_then(|a| a.json())).await;
 
-        if data.pairs0.len() == 0 && data.pairs1.len() == 0 {
+        if data.pairs0.is_empty() && data.pairs1.is_empty() {
             break;
         }
 
@@ -225,11 +192,15 @@ async fn uniswap_pairs(
     pairs
 }
 
-async fn balancer_pools(client: &Client, uniswap_pairs: &[(H160, Token, Token)]) -> Vec<Vec<H160>> {
+async fn balancer_pools(
+    client: &Client,
+    url: &str,
+    uniswap_pairs: &[(H160, Token, Token)],
+) -> Vec<Vec<H160>> {
     let mut pools = vec![];
     let mut count = 0;
 
-    for (index, (_, token0, token1)) in uniswap_pairs.into_iter().enumerate() {
+    for (index, (_, token0, token1)) in uniswap_pairs.iter().enumerate() {
         let query = BalancerGetPools::build_query(balancer_get_pools::Variables {
             tokens: vec![
                 format!("{:?}", token0.address),
@@ -237,14 +208,8 @@ async fn balancer_pools(client: &Client, uniswap_pairs: &[(H160, Token, Token)])
             ],
         });
 
-        let data: balancer_get_pools::ResponseData = send(&|| {
-            client
-                .post(BALANCER_URL)
-                .json(&query)
-                .send()
-                .and_then(|a| a.json())
-        })
-        .await;
+        let data: balancer_get_pools::ResponseData =
+            send(&|| client.post(url).json(&query).send().and_then(|a| a.json())).await;
 
         let mut valid_pools = vec![];
 
@@ -303,10 +268,10 @@ fn build_pairs(uniswap_pairs: Vec<(H160, Token, Token)>, balancer_pools: Vec<Vec
     for ((uniswap, token0, token1), balancers) in uniswap_pairs.into_iter().zip(balancer_pools) {
         for balancer in balancers {
             pairs.push(Pair {
-                token0: token0.address.clone(),
-                token1: token1.address.clone(),
-                balancer_pool: balancer.clone(),
-                uniswap_pair: uniswap.clone(),
+                token0: token0.address,
+                token1: token1.address,
+                balancer_pool: balancer,
+                uniswap_pair: uniswap,
             });
         }
 
@@ -317,7 +282,7 @@ fn build_pairs(uniswap_pairs: Vec<(H160, Token, Token)>, balancer_pools: Vec<Vec
     tokens.sort_unstable_by_key(|t| t.address);
     tokens.dedup_by_key(|t| t.address);
 
-    pairs.sort_unstable_by(|p1, p2| p1.cmp(&p2));
+    pairs.sort_unstable();
     pairs.dedup_by(|p1, p2| p1 == p2);
 
     Pairs { tokens, pairs }
@@ -327,18 +292,33 @@ fn build_pairs(uniswap_pairs: Vec<(H160, Token, Token)>, balancer_pools: Vec<Vec
 async fn main() {
     env_logger::init();
 
-    let weth_address = H160::from_str(WETH_ADDRESS).expect("weth address parsing failed");
-    let allowed_tokens = ALLOWED_TOKENS
-        .iter()
-        .copied()
-        .map(H160::from_str)
-        .collect::<Result<Vec<_>, _>>()
-        .expect("allowed tokens parsing failed");
+    let network_name = std::env::var("ARBRITO_NETWORK").unwrap_or_else(|_| "mainnet".to_owned());
+    let network = Networks::select(&network_name).expect("failed selecting network");
+
+    // The subgraphs can't tell which chain they index, so the node has to.
+    let web3_ipc_path = std::env::var("WEB3_IPC_PATH").expect("where's the ipc");
+    let web3 = Web3::new(Ipc::new(web3_ipc_path).await.expect("ipc failed"));
+    network.check_chain_id(&web3).await;
+
+    let uniswap_url = network
+        .uniswap_subgraph
+        .as_ref()
+        .expect("no uniswap subgraph on network");
+    let balancer_url = network
+        .balancer_subgraph
+        .as_ref()
+        .expect("no balancer subgraph on network");
 
     let client = reqwest::Client::new();
 
-    let uniswap_pairs = uniswap_pairs(&client, weth_address, &allowed_tokens).await;
-    let balancer_pools = balancer_pools(&client, &uniswap_pairs).await;
+    let uniswap_pairs = uniswap_pairs(
+        &client,
+        uniswap_url,
+        network.wrapped_native,
+        &network.allowed_tokens,
+    )
+    .await;
+    let balancer_pools = balancer_pools(&client, balancer_url, &uniswap_pairs).await;
 
     let pairs = build_pairs(uniswap_pairs, balancer_pools);
     log::info!("save | started");
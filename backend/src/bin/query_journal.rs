@@ -0,0 +1,73 @@
+use pooller::{journal::Journal, Pairs};
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use web3::types::U256;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DEFAULT_DAYS: u64 = 7;
+
+/// Lists the pairs found net profitable over the last days, from the journal
+/// watch_pairs keeps: `query_journal <journal path> [days]`.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .expect("usage: query_journal <journal path> [days]");
+    let days = args.next().map_or(DEFAULT_DAYS, |days| {
+        days.parse().expect("days must be a number")
+    });
+
+    let journal = Journal::open(&path).expect("failed opening journal");
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock before the epoch")
+        .as_secs();
+    let pairs = journal
+        .net_profitable_pairs(now.saturating_sub(days * SECONDS_PER_DAY))
+        .expect("failed querying journal");
+
+    // Names the pairs by their tokens when the pairs file is around.
+    let names: HashMap<_, _> = match Pairs::read() {
+        Ok(Pairs { tokens, pairs }) => {
+            let symbols: HashMap<_, _> = tokens.iter().map(|t| (t.address, &t.symbol)).collect();
+            pairs
+                .iter()
+                .map(|pair| {
+                    let name = format!("{}/{}", symbols[&pair.token0], symbols[&pair.token1]);
+                    ((pair.uniswap_pair, pair.balancer_pool), name)
+                })
+                .collect()
+        }
+        Err(_) => HashMap::new(),
+    };
+
+    if pairs.is_empty() {
+        println!("No pair was net profitable in the last {} days", days);
+        return;
+    }
+
+    println!(
+        "{} pairs were net profitable in the last {} days",
+        pairs.len(),
+        days
+    );
+    for pair in pairs {
+        let weth = U256::exp10(18);
+        println!(
+            "{:<12} uniswap {:?} balancer {:?}: {} times, {} executed, best {}.{:04} WETH, last at #{}",
+            names
+                .get(&(pair.uniswap_pair, pair.balancer_pool))
+                .map_or("?", String::as_str),
+            pair.uniswap_pair,
+            pair.balancer_pool,
+            pair.attempts,
+            pair.executed,
+            (pair.best_weth_profit / weth).as_u128(),
+            (pair.best_weth_profit % weth / U256::exp10(14)).as_u128(),
+            pair.last_block_number,
+        );
+    }
+}
@@ -1,39 +1,71 @@
 use colored::Colorize;
 use ethcontract::{
+    dyns::DynMethodBuilder,
     errors::{ExecutionError, MethodError},
-    Account, BlockId, BlockNumber, GasPrice, Password, TransactionCondition,
+    transaction::{Transaction, TransactionResult},
+    Account, BlockId, BlockNumber, GasPrice, Void,
 };
 use futures::{future::ready, stream::FuturesUnordered, FutureExt};
 use itertools::Itertools;
 use pooller::{
+    accounts::AccountPool,
+    admin::{self, Conflict, Control, GasScales, PairId},
+    balancer_out_given_in,
+    competitors::{self, Competitors, Learner},
+    dashboard::{self, Dashboard},
+    gas::{
+        Bump, Conditions, GasStrategy, Opportunity, Percentile, ProfitShare, RecentGasPrices,
+        ScaledGasPrice,
+    },
     gen::{Arbrito, BalancerPool, UniswapPair},
-    latest_block::LatestBlock,
+    journal::{self, AttemptResult, Journal},
+    latest_block::{Head, LatestBlock},
+    ledger::{self, Ledger, Mined, Outcome, Status},
     max_profit,
+    mempool::{Insertion, Mempool},
+    metadata::{fetch_decimals, BalancerPoolMetadata, UniswapPairMetadata},
+    metrics,
+    mock_transport::MockTransport,
+    network::Networks,
     pending_tx::PendingTx,
-    uniswap_out_given_in, Pairs, Token,
+    preflight::{self, Revert},
+    recording::{self, Event, Recorder},
+    relay::Relay,
+    select_disjoint,
+    settings::Settings,
+    signer::Signer,
+    state::{
+        BalancerPoolBase, BalancerPoolResolved, StateCache, UniswapPairBase, UniswapPairResolved,
+    },
+    uniswap_in_given_out, uniswap_out_given_in, Candidate, Pairs, Token,
 };
 use std::{
     collections::{HashMap, HashSet},
-    str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex as StdMutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::sync::{mpsc, Mutex, OwnedMutexGuard};
 use web3::{
-    futures::{future::join_all, StreamExt},
+    futures::{
+        future::{join_all, try_join_all},
+        StreamExt,
+    },
+    signing::keccak256,
     transports::Ipc,
     types::U64,
-    types::{TransactionId, H160, U256},
+    types::{Bytes, TransactionId, TransactionReceipt, H160, H256, U256},
     Web3,
 };
 
-const WETH_ADDRESS: &str = "C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
-const ARBRITO_ADDRESS: &str = "3FE133c5b1Aa156bF7D8Cf3699794d09Ef911ec1";
-const EXECUTOR_ADDRESS: &str = "Af43007aD675D6C72E96905cf4d8acB58ba0E041";
-const UNISWAP_ROUTER_ADDRESS: &str = "7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
-const EXPECTED_GAS_USAGE: u128 = 350_000;
-const MAX_GAS_USAGE: u128 = 400_000;
-const MIN_GAS_SCALE: u8 = 2;
-const MAX_GAS_SCALE: u8 = 5;
+const STATE_CHECKSUM_INTERVAL: u64 = 100;
+const METADATA_REFRESH_INTERVAL: u64 = 1000;
+const PENDING_TX_TTL: u64 = 3;
+const COMPETITOR_LEARN_THRESHOLD: usize = 3;
+const BUNDLE_TARGET_BLOCKS: u64 = 2;
+const DROPPED_TX_TTL: u64 = 10;
+const RECENT_GAS_PRICE_BLOCKS: usize = 20;
+const TOP_OPPORTUNITIES: usize = 10;
+const DASHBOARD_REDRAW_INTERVAL: Duration = Duration::from_millis(500);
 
 fn format_amount_colored(token: &Token, amount: U256) -> String {
     let string = format_amount(token, amount);
@@ -63,10 +95,16 @@ fn format_amount(token: &Token, amount: U256) -> String {
     )
 }
 
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
 fn format_block_number(number: U64) -> String {
     format!(
         "{}{}",
-        if number.as_u64() % 2 == 0 {
+        if number.as_u64().is_multiple_of(2) {
             "#".bright_magenta()
         } else {
             "#".bright_cyan()
@@ -77,11 +115,10 @@ fn format_block_number(number: U64) -> String {
 
 #[derive(Debug, Clone, Copy)]
 struct Block {
-    id: BlockId,
     number: U64,
     gas_price: U256,
+    /// Most an executor account has left to spend.
     balance: U256,
-    nonce: U256,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -89,39 +126,25 @@ struct Config {
     expected_gas_usage: U256,
     max_gas_usage: U256,
     min_gas_scale: u8,
-    max_gas_scale: u8,
+}
+
+/// What gas bids are made with, refreshed every block.
+#[derive(Clone)]
+struct Gas {
+    strategy: Arc<dyn GasStrategy>,
+    recent_gas_prices: Arc<Vec<U256>>,
+    pending_txs: Arc<Vec<PendingTx>>,
 }
 
 struct Context {
     block: Block,
     config: Config,
+    gas: Gas,
     pairs: HashMap<H160, UniswapPairResolved>,
     pools: HashMap<H160, BalancerPoolResolved>,
 }
 
-struct UniswapPairBase {
-    contract: UniswapPair,
-    address: H160,
-    token0: H160,
-}
-
-struct UniswapPairResolved {
-    reserve0: U256,
-    reserve1: U256,
-    token0: H160,
-}
-
-struct BalancerPoolBase {
-    contract: BalancerPool,
-    tokens: HashSet<H160>,
-    address: H160,
-}
-
-struct BalancerPoolResolved {
-    balances: HashMap<H160, U256>,
-    swap_fee: U256,
-}
-
+#[allow(clippy::enum_variant_names)]
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 enum ArbritageResult {
     NotProfit,
@@ -134,6 +157,7 @@ enum ArbritageResult {
         min_gas_price: U256,
         max_gas_price: U256,
         amount: U256,
+        bump: Bump,
     },
 }
 
@@ -144,6 +168,79 @@ struct ArbritageAttempt {
     result: ArbritageResult,
     config: Config,
     block: Block,
+    /// Sized on state projected with a pending swap, which the chain doesn't
+    /// have yet to simulate the attempt on.
+    backrun: bool,
+}
+
+/// An attempt made in dry runs, checked against the following block instead
+/// of being sent.
+struct PaperTrade {
+    attempt: ArbritageAttempt,
+    amount: U256,
+    gas_price: U256,
+    ctx: Arc<Context>,
+}
+
+/// How executions reach the chain.
+#[derive(Clone)]
+enum Submission {
+    /// Dry runs, checking attempts against the following block instead.
+    Paper(mpsc::UnboundedSender<PaperTrade>),
+    /// Signed locally and broadcast raw through the node.
+    Public,
+    /// Signed locally and sent privately as a bundle, paying the miner a share
+    /// of the expected profit through the gas price, up to the max one. Arbrito
+    /// has no way to pay the coinbase directly.
+    Bundle { relay: Relay, bid_percent: u8 },
+}
+
+/// Executor accounts, shared by the executions in flight.
+#[derive(Clone)]
+struct Accounts {
+    pool: Arc<StdMutex<AccountPool>>,
+    signers: Arc<HashMap<H160, Signer>>,
+}
+
+impl Accounts {
+    fn pool(&self) -> MutexGuard<'_, AccountPool> {
+        self.pool.lock().expect("account pool lock poisoned")
+    }
+
+    /// The signer of the least busy account that can pay the cost.
+    fn pick(&self, cost: U256) -> Option<Signer> {
+        let address = self.pool().pick(cost)?;
+        self.signers.get(&address).cloned()
+    }
+}
+
+/// How a sent execution ended up.
+enum Sent {
+    /// Nothing went out, so the nonce can be handed out again.
+    Nothing,
+    /// Went out as a bundle that wasn't included.
+    NotIncluded,
+    /// Broadcast, but no send came back with a receipt, so one may still be
+    /// mined. The nonce is kept until the account sync takes it as dropped.
+    NotMined,
+    /// Mined as the send at the given gas price, the first one being 0.
+    Mined {
+        receipt: Box<TransactionReceipt>,
+        gas_price: U256,
+        replacement: usize,
+    },
+    /// Went out, but how it ended up couldn't be told.
+    Unknown,
+}
+
+/// What executions are carried out with, shared by the ones in flight.
+#[derive(Clone)]
+struct Executor {
+    arbrito: Arbrito,
+    accounts: Accounts,
+    submission: Submission,
+    outcomes_tx: mpsc::UnboundedSender<Outcome>,
+    control: Control,
 }
 
 #[derive(Debug, Clone)]
@@ -155,58 +252,100 @@ struct ArbritagePair {
     weth: Token,
 }
 
-impl Block {
-    async fn fetch(web3: &Web3<Ipc>, number: U64, addr: H160) -> Block {
-        let eth = web3.eth();
-        let (nonce, balance, gas_price) = tokio::join!(
-            eth.transaction_count(addr, Some(BlockNumber::Number(number))),
-            eth.balance(addr, Some(BlockNumber::Number(number))),
-            eth.gas_price(),
-        );
+impl Context {
+    /// Applies the pending transactions paying at least the given gas price,
+    /// since those are the ones expected to be mined ahead of an execution.
+    fn project<'a>(
+        mut self,
+        pending_txs: impl Iterator<Item = &'a PendingTx>,
+        min_gas_price: U256,
+        pair_index: &HashMap<(H160, H160), H160>,
+    ) -> Context {
+        let mut pending_txs: Vec<_> = pending_txs
+            .filter(|tx| tx.gas_price >= min_gas_price)
+            .collect();
 
-        Block {
-            id: BlockId::Number(BlockNumber::Number(number)),
-            gas_price: gas_price.expect("failed fetching gas_price"),
-            balance: balance.expect("failed fetching balance"),
-            nonce: nonce.expect("failed fetching nonce"),
-            number,
+        pending_txs.sort_by_key(|tx| std::cmp::Reverse(tx.gas_price));
+
+        for pending_tx in pending_txs {
+            pending_tx.apply(&mut self.pairs, &mut self.pools, pair_index);
         }
+
+        self
     }
 }
 
-impl UniswapPairBase {
-    async fn resolve(&self, block: Block) -> (H160, UniswapPairResolved) {
-        let req = self.contract.get_reserves().block(block.id);
-        let (reserve0, reserve1, _) = req.call().await.expect("unable to fetch reserves");
+impl Block {
+    /// Syncs the executor accounts to the new head.
+    fn sync(head: &Head, accounts: &Accounts) -> Block {
+        let mut pool = accounts.pool();
+        for &(address, nonce, balance) in &head.accounts {
+            metrics::EXECUTOR_BALANCE
+                .with_label_values(&[&format!("{:?}", address)])
+                .set(balance.low_u128() as f64 / 1e18);
+
+            for dropped in pool.sync(address, head.number, nonce, balance) {
+                log::info!(
+                    "{} Execution transaction {:?} #{} was dropped",
+                    format_block_number(head.number),
+                    address,
+                    dropped
+                );
+            }
+        }
 
-        (
-            self.address,
-            UniswapPairResolved {
-                reserve0: U256::from(reserve0),
-                reserve1: U256::from(reserve1),
-                token0: self.token0,
-            },
-        )
+        Block {
+            gas_price: head.gas_price,
+            balance: pool.max_available(),
+            number: head.number,
+        }
     }
 }
 
-impl BalancerPoolBase {
-    async fn resolve(&self, block: Block) -> (H160, BalancerPoolResolved) {
-        let req = self.contract.get_swap_fee().block(block.id);
-        let swap_fee = req.call().await.expect("unable to fetch swap fee");
+/// The result's value, counting it as an rpc error of the call otherwise.
+fn counted<T>(call: &str, result: Result<T, web3::Error>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(err) => {
+            metrics::RPC_ERRORS.with_label_values(&[call]).inc();
+            log::debug!("Failed {} request: {}", call, err);
+            None
+        }
+    }
+}
 
-        let futs = self.tokens.iter().copied().map(|t| {
-            let req = self.contract.get_balance(t).block(block.id);
-            req.call().map(move |r| (t, r.expect("unable to balancer")))
-        });
+fn redraw(dashboard: &mut Option<Dashboard>, control: &Control) {
+    if let Some(dashboard) = dashboard {
+        if let Err(err) = dashboard.draw(&control.status()) {
+            log::warn!("Failed drawing dashboard: {}", err);
+        }
+    }
+}
 
-        let balances = join_all(futs).await.into_iter().collect();
-        (self.address, BalancerPoolResolved { balances, swap_fee })
+fn record(recorder: &mut Option<Recorder>, event: &Event) {
+    if let Some(recorder) = recorder {
+        if let Err(err) = recorder.record(event) {
+            log::warn!("Failed recording event: {}", err);
+        }
     }
 }
 
 impl ArbritagePair {
-    fn run(&self, borrow_token: &Token, profit_token: &Token, ctx: &Context) -> ArbritageResult {
+    fn id(&self) -> PairId {
+        PairId {
+            uniswap_pair: self.uniswap_pair,
+            balancer_pool: self.balancer_pool,
+        }
+    }
+
+    /// Uniswap reserves and Balancer balances of the tokens, in the order the
+    /// calc functions take them, along with the pool's swap fee.
+    fn balances(
+        &self,
+        borrow_token: &Token,
+        profit_token: &Token,
+        ctx: &Context,
+    ) -> (U256, U256, U256, U256, U256) {
         let pair = ctx
             .pairs
             .get(&self.uniswap_pair)
@@ -233,13 +372,18 @@ impl ArbritagePair {
             .get(&profit_token.address)
             .expect("missing profit token balance");
 
-        let (borrow_amount, payback_amount, profit) =
-            match max_profit(U256::from(ri), U256::from(ro), *bi, *bo, pool.swap_fee) {
-                None => return ArbritageResult::NotProfit,
-                Some(a) => a,
-            };
+        (ri, ro, *bi, *bo, pool.swap_fee)
+    }
 
-        let weth_profit = if profit_token.address == self.weth.address {
+    fn weth_profit(
+        &self,
+        profit_token: &Token,
+        borrow_amount: U256,
+        payback_amount: U256,
+        profit: U256,
+        ctx: &Context,
+    ) -> U256 {
+        if profit_token.address == self.weth.address {
             profit
         } else {
             let profit_pair_address = profit_token
@@ -249,15 +393,9 @@ impl ArbritagePair {
             let profit_pair = ctx.pairs.get(&profit_pair_address).unwrap();
 
             let (mut ro, mut ri) = if profit_pair.token0 == profit_token.address {
-                (
-                    U256::from(profit_pair.reserve1),
-                    U256::from(profit_pair.reserve0),
-                )
+                (profit_pair.reserve1, profit_pair.reserve0)
             } else {
-                (
-                    U256::from(profit_pair.reserve0),
-                    U256::from(profit_pair.reserve1),
-                )
+                (profit_pair.reserve0, profit_pair.reserve1)
             };
 
             if profit_pair_address == self.uniswap_pair {
@@ -266,15 +404,70 @@ impl ArbritagePair {
             }
 
             uniswap_out_given_in(ri, ro, profit)
+        }
+    }
+
+    /// Weth profit of borrowing the given amount, as long as it still pays
+    /// the loan back.
+    fn realized(
+        &self,
+        borrow_token: &Token,
+        profit_token: &Token,
+        amount: U256,
+        ctx: &Context,
+    ) -> Option<U256> {
+        let (ri, ro, bi, bo, s) = self.balances(borrow_token, profit_token, ctx);
+        if amount >= ro {
+            return None;
+        }
+
+        let payback_amount = uniswap_in_given_out(ri, ro, amount);
+        let sell_amount = balancer_out_given_in(bi, bo, s, amount);
+        let profit = sell_amount.checked_sub(payback_amount)?;
+
+        Some(self.weth_profit(profit_token, amount, payback_amount, profit, ctx))
+    }
+
+    fn run(&self, borrow_token: &Token, profit_token: &Token, ctx: &Context) -> ArbritageResult {
+        let (ri, ro, bi, bo, s) = self.balances(borrow_token, profit_token, ctx);
+        let (borrow_amount, payback_amount, profit) = match max_profit(ri, ro, bi, bo, s) {
+            None => return ArbritageResult::NotProfit,
+            Some(a) => a,
         };
 
-        let min_gas_price = ctx.block.gas_price * ctx.config.min_gas_scale;
-        let max_gas_price = (ctx.block.gas_price * ctx.config.max_gas_scale)
-            .min(ctx.block.balance / ctx.config.max_gas_usage)
-            .min(weth_profit / ctx.config.expected_gas_usage);
+        let weth_profit =
+            self.weth_profit(profit_token, borrow_amount, payback_amount, profit, ctx);
+
+        let competitors: Vec<_> = ctx
+            .gas
+            .pending_txs
+            .iter()
+            .filter(|tx| {
+                tx.conflicts(
+                    profit_token.address,
+                    borrow_token.address,
+                    self.uniswap_pair,
+                    self.balancer_pool,
+                )
+            })
+            .map(|tx| tx.gas_price)
+            .collect();
+
+        let opportunity = Opportunity {
+            weth_profit,
+            expected_gas_usage: ctx.config.expected_gas_usage,
+            max_gas_usage: ctx.config.max_gas_usage,
+        };
+        let conditions = Conditions {
+            gas_price: ctx.block.gas_price,
+            balance: ctx.block.balance,
+            competitors: &competitors,
+            recent_gas_prices: &ctx.gas.recent_gas_prices,
+        };
+        let bid = ctx.gas.strategy.bid(&opportunity, &conditions);
 
-        if max_gas_price < min_gas_price {
-            if ctx.block.balance / ctx.config.max_gas_usage < min_gas_price {
+        if !bid.viable() {
+            if conditions.affordable(ctx.config.max_gas_usage) < bid.initial {
                 log::warn!(
                     "balance cannot pay min gas price. attempt won't be correctly calculated"
                 );
@@ -286,9 +479,10 @@ impl ArbritagePair {
         } else {
             ArbritageResult::NetProfit {
                 amount: borrow_amount,
-                min_gas_price,
-                max_gas_price,
+                min_gas_price: bid.initial,
+                max_gas_price: bid.max,
                 weth_profit,
+                bump: bid.bump,
             }
         }
     }
@@ -301,6 +495,7 @@ impl ArbritagePair {
                 tokens: (self.token0.clone(), self.token1.clone()),
                 config: ctx.config,
                 block: ctx.block,
+                backrun: false,
             },
             ArbritageAttempt {
                 pair: self.clone(),
@@ -308,58 +503,199 @@ impl ArbritagePair {
                 tokens: (self.token1.clone(), self.token0.clone()),
                 config: ctx.config,
                 block: ctx.block,
+                backrun: false,
             },
         ]
     }
 }
 
+impl ArbritageAttempt {
+    fn journal_entry(&self, executed: bool, timestamp: u64) -> journal::Entry {
+        let (result, amount, weth_profit, gas_prices) = match self.result {
+            ArbritageResult::NotProfit => (AttemptResult::NotProfit, 0.into(), 0.into(), None),
+            ArbritageResult::GrossProfit {
+                weth_profit,
+                amount,
+            } => (AttemptResult::GrossProfit, amount, weth_profit, None),
+            ArbritageResult::NetProfit {
+                weth_profit,
+                min_gas_price,
+                max_gas_price,
+                amount,
+                ..
+            } => (
+                AttemptResult::NetProfit,
+                amount,
+                weth_profit,
+                Some((min_gas_price, max_gas_price)),
+            ),
+        };
+
+        journal::Entry {
+            timestamp,
+            block_number: self.block.number.as_u64(),
+            uniswap_pair: self.pair.uniswap_pair,
+            balancer_pool: self.pair.balancer_pool,
+            borrow_token: self.tokens.0.address,
+            profit_token: self.tokens.1.address,
+            result,
+            amount,
+            weth_profit,
+            min_gas_price: gas_prices.map(|(min, _)| min),
+            max_gas_price: gas_prices.map(|(_, max)| max),
+            executed,
+        }
+    }
+
+    /// Pins the gas price to the one of the swap being backrun, so the execution
+    /// lands right after it. Only net profitable attempts can afford it.
+    fn backrun(mut self, gas_price: U256) -> Option<ArbritageAttempt> {
+        match &mut self.result {
+            ArbritageResult::NetProfit {
+                min_gas_price,
+                max_gas_price,
+                ..
+            } if gas_price <= *max_gas_price => {
+                *min_gas_price = gas_price;
+                *max_gas_price = gas_price;
+                self.backrun = true;
+                Some(self)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn tradeable_attempts(
+    arbritage_pairs: &[ArbritagePair],
+    balancer_pool_bases: &HashMap<H160, BalancerPoolBase>,
+    ctx: &Context,
+) -> Vec<ArbritageAttempt> {
+    arbritage_pairs
+        .iter()
+        .filter(|pair| {
+            let pool = &balancer_pool_bases[&pair.balancer_pool];
+            pool.metadata
+                .tradeable(pair.token0.address, pair.token1.address)
+        })
+        .flat_map(|pair| pair.attempts(ctx))
+        .collect()
+}
+
 async fn executor(
-    arbrito: Arbrito,
-    from_address: H160,
+    executor: Executor,
     execution_lock: Arc<Mutex<()>>,
     mut pending_txs_rx: mpsc::UnboundedReceiver<PendingTx>,
-    mut execution_rx: mpsc::UnboundedReceiver<(ArbritageAttempt, Context)>,
+    mut execution_rx: mpsc::UnboundedReceiver<(Vec<ArbritageAttempt>, Context)>,
 ) {
-    let mut executing_attempt: Option<(ArbritageAttempt, mpsc::UnboundedSender<PendingTx>)> = None;
+    let mut executing_attempts: Vec<(ArbritageAttempt, mpsc::UnboundedSender<PendingTx>)> = vec![];
 
     loop {
         tokio::select! {
             pending_tx = pending_txs_rx.recv() => if let Some(swap) = pending_tx {
-                if let Ok(_) = execution_lock.try_lock() {
+                if execution_lock.try_lock().is_ok() {
                     continue;
                 }
 
-                if let Some((attempt, conflicting_txs_tx)) = &executing_attempt {
-                    if swap.conflicts(attempt.tokens.1.address, attempt.tokens.0.address, attempt.pair.balancer_pool) {
-                        conflicting_txs_tx.send(swap).expect("execute task died");
+                // Executions that are done have dropped their receiver.
+                executing_attempts.retain(|(attempt, conflicting_txs_tx)| {
+                    if !swap.conflicts(attempt.tokens.1.address, attempt.tokens.0.address, attempt.pair.uniswap_pair, attempt.pair.balancer_pool) {
+                        return true;
                     }
-                }
+
+                    metrics::CONFLICTS.with_label_values(&[swap.competitor.as_deref().unwrap_or("unknown")]).inc();
+                    executor.control.conflict(Conflict {
+                        pair: attempt.pair.id(),
+                        hash: swap.hash,
+                        gas_price: swap.gas_price,
+                        competitor: swap.competitor.clone(),
+                    });
+                    conflicting_txs_tx.send(swap.clone()).is_ok()
+                });
             },
-            execution = execution_rx.recv() => if let Some((attempt, ctx)) = execution {
+            execution = execution_rx.recv() => if let Some((attempts, ctx)) = execution {
                 if let Ok(guard) = execution_lock.clone().try_lock_owned() {
-                    let (conflicting_txs_tx, conflicting_txs_rx) = mpsc::unbounded_channel();
-                    executing_attempt = Some((attempt.clone(), conflicting_txs_tx));
-                    tokio::spawn(execute(guard, conflicting_txs_rx, attempt, arbrito.clone(), from_address, ctx));
+                    // The lock is held until every attempt of the batch is done.
+                    let guard = Arc::new(guard);
+                    let ctx = Arc::new(ctx);
+                    executing_attempts.clear();
+
+                    for attempt in attempts {
+                        let (conflicting_txs_tx, conflicting_txs_rx) = mpsc::unbounded_channel();
+                        executing_attempts.push((attempt.clone(), conflicting_txs_tx));
+                        tokio::spawn(execute(guard.clone(), conflicting_txs_rx, attempt, ctx.clone(), executor.clone()));
+                    }
                 }
             }
         }
     }
 }
 
+/// The call performing the attempt, pinned to the state it was computed on.
+fn perform(
+    arbrito: &Arbrito,
+    attempt: &ArbritageAttempt,
+    amount: U256,
+    ctx: &Context,
+) -> DynMethodBuilder<Void> {
+    let borrow = if attempt.pair.token0.address == attempt.tokens.0.address {
+        0
+    } else {
+        1
+    };
+
+    let pair = ctx
+        .pairs
+        .get(&attempt.pair.uniswap_pair)
+        .expect("missing context uniswap pair");
+
+    let pool = ctx
+        .pools
+        .get(&attempt.pair.balancer_pool)
+        .expect("missing context balancer pool");
+
+    let balance0 = *pool.balances.get(&attempt.pair.token0.address).unwrap();
+    let balance1 = *pool.balances.get(&attempt.pair.token1.address).unwrap();
+
+    arbrito.perform(
+        borrow,
+        amount,
+        attempt.pair.uniswap_pair,
+        attempt.pair.balancer_pool,
+        attempt.pair.token0.address,
+        attempt.pair.token1.address,
+        pair.reserve0,
+        pair.reserve1,
+        balance0,
+        balance1,
+    )
+}
+
+/// Executes one attempt of a batch, from an executor account that can pay
+/// for it, reporting how it ended up once it did. The nonce is given back
+/// when nothing goes out with it.
 async fn execute(
-    _: OwnedMutexGuard<()>,
+    _: Arc<OwnedMutexGuard<()>>,
     mut conflicting_txs_rx: mpsc::UnboundedReceiver<PendingTx>,
     attempt: ArbritageAttempt,
-    arbrito: Arbrito,
-    from_address: H160,
-    ctx: Context,
+    ctx: Arc<Context>,
+    executor: Executor,
 ) {
-    let (amount, min_gas_price, max_gas_price) = match attempt.result {
+    let Executor {
+        arbrito,
+        accounts,
+        submission,
+        outcomes_tx,
+        control,
+    } = executor;
+
+    let (amount, min_gas_price, max_gas_price, weth_profit, bump) = match attempt.result {
         ArbritageResult::NetProfit {
             min_gas_price,
             max_gas_price,
             amount,
-            ..
+            weth_profit,
+            bump,
         } => {
             log::debug!(
                 "Token addresses = {} {}",
@@ -369,7 +705,7 @@ async fn execute(
             log::debug!("UniswapPool = {}", attempt.pair.uniswap_pair);
             log::debug!("BalancerPool = {}", attempt.pair.balancer_pool);
 
-            (amount, min_gas_price, max_gas_price)
+            (amount, min_gas_price, max_gas_price, weth_profit, bump)
         }
         _ => {
             log::error!(
@@ -380,73 +716,251 @@ async fn execute(
         }
     };
 
-    let borrow = if attempt.pair.token0.address == attempt.tokens.0.address {
-        0
+    if let Submission::Paper(paper_trades_tx) = &submission {
+        let trade = PaperTrade {
+            gas_price: min_gas_price,
+            attempt,
+            amount,
+            ctx,
+        };
+
+        if paper_trades_tx.send(trade).is_err() {
+            log::error!("Paper trades rx died");
+        }
+        return;
+    }
+
+    let cost = attempt.config.max_gas_usage * max_gas_price;
+    let signer = match accounts.pick(cost) {
+        Some(signer) => signer,
+        None => {
+            log::info!(
+                "{} No executor account can pay for the execution",
+                format_block_number(attempt.block.number)
+            );
+            return;
+        }
+    };
+    let address = signer.address();
+
+    let data = perform(&arbrito, &attempt, amount, &ctx)
+        .tx
+        .data
+        .unwrap_or_default();
+
+    // Backruns only go through once the swap ahead of them is mined, so there
+    // is no state to simulate them on and they are sent as they are.
+    let gas_limit = if attempt.backrun {
+        attempt.config.max_gas_usage
     } else {
-        1
+        let simulation = preflight::simulate(
+            &arbrito.raw_instance().web3(),
+            address,
+            arbrito.address(),
+            data.clone(),
+            BlockNumber::Number(attempt.block.number),
+        )
+        .await;
+
+        let gas = match simulation {
+            Ok(gas) => gas,
+            Err(err) => {
+                match Revert::from_error(&err) {
+                    Some(revert) => log::info!(
+                        "{} Simulated execution reverted: {:?}",
+                        format_block_number(attempt.block.number),
+                        revert
+                    ),
+                    None => {
+                        metrics::RPC_ERRORS.with_label_values(&["simulate"]).inc();
+                        log::warn!(
+                            "{} Failed simulating execution: {}",
+                            format_block_number(attempt.block.number),
+                            err
+                        )
+                    }
+                }
+                return;
+            }
+        };
+
+        // Only the gas is checked, against the profit expected off chain.
+        if gas > attempt.config.max_gas_usage || weth_profit < gas * min_gas_price {
+            log::info!(
+                "{} Simulated execution takes {} gas, more than the expected profit pays for",
+                format_block_number(attempt.block.number),
+                gas
+            );
+            return;
+        }
+
+        // Leaves some room for the estimate being off, as it only holds for
+        // the state the attempt was computed on.
+        (gas + gas / 5).min(attempt.config.max_gas_usage)
     };
 
-    let pair = ctx
-        .pairs
-        .get(&attempt.pair.uniswap_pair)
-        .expect("missing context uniswap pair");
+    // Reserved only once the execution is going out, so that nonces given
+    // back don't leave gaps below the ones sent.
+    let nonce = match accounts.pool().reserve(address, cost) {
+        Some(nonce) => nonce,
+        None => {
+            log::info!(
+                "{} Executor account {:?} can't pay for the execution anymore",
+                format_block_number(attempt.block.number),
+                address
+            );
+            return;
+        }
+    };
 
-    let pool = ctx
-        .pools
-        .get(&attempt.pair.balancer_pool)
-        .expect("missing context balancer pool");
+    let pair = attempt.pair.id();
+    control.start_execution(attempt.journal_entry(true, unix_time()), address);
+
+    // Bundles are simulated on their own, and can't follow a swap they don't
+    // include, so backruns are always broadcast.
+    let sent = match submission {
+        Submission::Bundle { relay, bid_percent } if !attempt.backrun => {
+            let sign = |gas_price| {
+                perform(&arbrito, &attempt, amount, &ctx)
+                    .from(signer.account())
+                    .gas(gas_limit)
+                    .gas_price(GasPrice::Value(gas_price))
+                    .nonce(nonce)
+                    .tx
+                    .build()
+            };
 
-    let balance0 = *pool.balances.get(&attempt.pair.token0.address).unwrap();
-    let balance1 = *pool.balances.get(&attempt.pair.token1.address).unwrap();
+            let bid = |gas_used: u64| {
+                let bid = (weth_profit * bid_percent / 100 / gas_used.max(1))
+                    .max(min_gas_price)
+                    .min(max_gas_price);
+                control.sent(pair, bid);
+                bid
+            };
+
+            accounts.pool().sent(address, nonce, attempt.block.number);
+            execute_bundle(&relay, &arbrito, &attempt, sign, bid, min_gas_price).await
+        }
+        _ => {
+            execute_public(
+                &mut conflicting_txs_rx,
+                &attempt,
+                |gas_price| {
+                    accounts.pool().sent(address, nonce, attempt.block.number);
+                    control.sent(pair, gas_price);
+                    perform(&arbrito, &attempt, amount, &ctx)
+                        .from(signer.account())
+                        .gas(gas_limit)
+                        .gas_price(GasPrice::Value(gas_price))
+                        .nonce(nonce)
+                        .confirmations(0)
+                        .send()
+                },
+                min_gas_price,
+                max_gas_price,
+                bump,
+            )
+            .await
+        }
+    };
+    control.finish_execution(pair);
 
-    let send_tx = |gas_price| {
-        let arbrito = arbrito.clone();
-        let attempt = attempt.clone();
+    if !matches!(sent, Sent::Nothing) {
+        metrics::EXECUTIONS.with_label_values(&["sent"]).inc();
+    }
 
-        async move {
-            arbrito
-                .perform(
-                    borrow,
-                    amount,
-                    attempt.pair.uniswap_pair,
-                    attempt.pair.balancer_pool,
-                    attempt.pair.token0.address,
-                    attempt.pair.token1.address,
-                    pair.reserve0,
-                    pair.reserve1,
-                    balance0,
-                    balance1,
-                )
-                .from(Account::Locked(
-                    from_address,
-                    Password::new(std::env::var("ARBRITO_EXEC_PASSWORD").unwrap()),
-                    Some(TransactionCondition::Block(attempt.block.number.as_u64())),
-                ))
-                .gas(attempt.config.max_gas_usage)
-                .gas_price(GasPrice::Value(gas_price))
-                .nonce(attempt.block.nonce)
-                .confirmations(0)
-                .send()
-                .await
+    let status = match sent {
+        Sent::Nothing => {
+            accounts.pool().release(address, nonce);
+            return;
+        }
+        Sent::NotIncluded => {
+            accounts.pool().release(address, nonce);
+            Status::Dropped
         }
+        Sent::NotMined => Status::Dropped,
+        Sent::Mined {
+            receipt,
+            gas_price,
+            replacement,
+        } => {
+            settle(
+                &arbrito,
+                &attempt,
+                address,
+                data,
+                &receipt,
+                gas_price,
+                replacement,
+            )
+            .await
+        }
+        // Taken as gone, as the account sync forgets the nonce if it wasn't.
+        Sent::Unknown => {
+            accounts.pool().release(address, nonce);
+            Status::Unknown
+        }
+    };
+
+    let outcome = match &status {
+        Status::Succeeded { .. } => "succeeded",
+        Status::Reverted { .. } => "failed",
+        Status::Dropped => "dropped",
+        Status::Unknown => "unknown",
+    };
+    metrics::EXECUTIONS.with_label_values(&[outcome]).inc();
+
+    let outcome = Outcome {
+        timestamp: unix_time(),
+        block_number: attempt.block.number,
+        uniswap_pair: attempt.pair.uniswap_pair,
+        balancer_pool: attempt.pair.balancer_pool,
+        borrow_token: attempt.tokens.0.address,
+        profit_token: attempt.tokens.1.address,
+        expected_weth_profit: weth_profit,
+        status,
+    };
+
+    if outcomes_tx.send(outcome).is_err() {
+        log::error!("Outcomes rx died");
+    }
+}
+
+/// Broadcasts the execution, bumping its gas price over the conflicting
+/// transactions showing up, and waits for one of the sends to be mined.
+async fn execute_public<S, F>(
+    conflicting_txs_rx: &mut mpsc::UnboundedReceiver<PendingTx>,
+    attempt: &ArbritageAttempt,
+    send_tx: S,
+    min_gas_price: U256,
+    max_gas_price: U256,
+    bump: Bump,
+) -> Sent
+where
+    S: Fn(U256) -> F,
+    F: std::future::Future<Output = Result<TransactionResult, MethodError>>,
+{
+    let send_tx = |gas_price, replacement| {
+        send_tx(gas_price).map(move |result| (gas_price, replacement, result))
     };
 
     let mut txs = FuturesUnordered::new();
     let mut last_gas_price = min_gas_price;
-    txs.push(send_tx(last_gas_price));
+    let mut replacements = 0;
+    txs.push(send_tx(last_gas_price, replacements));
 
-    let receipts = loop {
+    let results = loop {
         tokio::select! {
-            receipt = txs.next(), if !txs.is_empty() => if let Some(receipt) = receipt {
-                let mut receipts: Vec<_> = txs.collect().await;
-                receipts.push(receipt);
-                break receipts;
+            result = txs.next(), if !txs.is_empty() => if let Some(result) = result {
+                let mut results: Vec<_> = txs.collect().await;
+                results.push(result);
+                break results;
             },
             conflicting_tx = conflicting_txs_rx.recv() => if let Some(conflicting_tx) = conflicting_tx {
-                let new_gas_price = conflicting_tx.gas_price + U256::exp10(9);
-                if last_gas_price < new_gas_price && new_gas_price <= max_gas_price {
+                if let Some(new_gas_price) = bump.next(last_gas_price, conflicting_tx.gas_price, max_gas_price) {
                     last_gas_price = new_gas_price;
-                    txs.push(send_tx(last_gas_price));
+                    replacements += 1;
+                    txs.push(send_tx(last_gas_price, replacements));
                     log::info!(
                         "{} Pumping up the gas on execution transaction: {} (due to {:?})",
                         format_block_number(attempt.block.number),
@@ -458,122 +972,505 @@ async fn execute(
         };
     };
 
-    if let Some(receipt) = receipts.iter().find_map(|r| r.as_ref().ok()) {
+    let mined = results
+        .into_iter()
+        .find_map(|(gas_price, replacement, result)| {
+            let receipt = match result {
+                Ok(TransactionResult::Receipt(receipt)) => Box::new(receipt),
+                Err(MethodError {
+                    inner: ExecutionError::Failure(receipt),
+                    ..
+                }) => receipt,
+                _ => return None,
+            };
+            Some(Sent::Mined {
+                receipt,
+                gas_price,
+                replacement,
+            })
+        });
+
+    mined.unwrap_or_else(|| {
         log::info!(
-            "{} {} Transaction hash {:?}",
+            "{} {}",
             format_block_number(attempt.block.number),
-            "Arbitrage execution succeeded!".bright_green().bold(),
-            receipt.hash()
+            "Arbitrage execution wasn't mined".red().dimmed(),
         );
-    } else if let Some(receipt) = receipts.iter().find_map(|r| match r {
-        Err(MethodError {
-            inner: ExecutionError::Failure(receipt),
-            ..
-        }) => Some(receipt),
-        _ => None,
-    }) {
-        log::info!(
-            "{} {} Transaction hash {:?}",
-            format_block_number(attempt.block.number),
-            "Arbitrage execution failed".red().dimmed(),
-            receipt.transaction_hash
+        Sent::NotMined
+    })
+}
+
+/// Looks into the receipt of a mined execution for what it paid and made, or
+/// why it reverted. The profit is taken from its own transfer logs, so other
+/// executions in the same block don't count towards it.
+async fn settle(
+    arbrito: &Arbrito,
+    attempt: &ArbritageAttempt,
+    from: H160,
+    data: Bytes,
+    receipt: &TransactionReceipt,
+    gas_price: U256,
+    replacement: usize,
+) -> Status {
+    let number = attempt.block.number;
+    let mined_in = receipt.block_number.unwrap_or(number);
+    let before = BlockNumber::Number(mined_in.saturating_sub(1.into()));
+
+    let mined = Mined {
+        hash: receipt.transaction_hash,
+        block_number: mined_in,
+        gas_used: receipt.gas_used.unwrap_or_default(),
+        gas_price,
+        replacement,
+    };
+
+    if receipt.status != Some(1.into()) {
+        let revert = match preflight::call(
+            &arbrito.raw_instance().web3(),
+            from,
+            arbrito.address(),
+            data,
+            before,
         )
-    } else {
+        .await
+        {
+            Ok(_) => None,
+            Err(err) => Revert::from_error(&err),
+        };
+
         log::info!(
-            "{} {}",
-            format_block_number(attempt.block.number),
+            "{} {} Transaction hash {:?} ({:?})",
+            format_block_number(number),
             "Arbitrage execution failed".red().dimmed(),
-        )
+            mined.hash,
+            revert
+        );
+        return Status::Reverted { mined, revert };
     }
-}
 
-#[tokio::main]
-async fn main() {
-    env_logger::init();
+    let profit = ledger::credited(
+        &receipt.logs,
+        attempt.tokens.1.address,
+        arbrito.address(),
+        attempt.pair.balancer_pool,
+        attempt.pair.uniswap_pair,
+    );
 
-    let web3_ipc_path = std::env::var("WEB3_IPC_PATH").expect("where's the ipc");
-    let web3 = Web3::new(Ipc::new(web3_ipc_path).await.expect("ipc failed"));
+    log::info!(
+        "{} {} Transaction hash {:?} (made {})",
+        format_block_number(number),
+        "Arbitrage execution succeeded!".bright_green().bold(),
+        mined.hash,
+        profit.map_or_else(
+            || "an unknown amount".to_owned(),
+            |profit| format_amount_colored(&attempt.tokens.1, profit)
+        )
+    );
+    Status::Succeeded { mined, profit }
+}
 
-    let Pairs { tokens, pairs } = Pairs::read().expect("pairs reading failed");
-    let tokens: Arc<HashMap<_, _>> = Arc::new(tokens.into_iter().map(|t| (t.address, t)).collect());
+/// Simulates the signed execution as a bundle with the relay, then sends it
+/// bidding for the gas it used for the next few blocks and waits for it.
+async fn execute_bundle<S, F>(
+    relay: &Relay,
+    arbrito: &Arbrito,
+    attempt: &ArbritageAttempt,
+    sign: S,
+    bid: impl Fn(u64) -> U256,
+    min_gas_price: U256,
+) -> Sent
+where
+    S: Fn(U256) -> F,
+    F: std::future::Future<Output = Result<Transaction, ExecutionError>>,
+{
+    let number = attempt.block.number;
+    let target = number + 1;
+    let last_target = number + BUNDLE_TARGET_BLOCKS;
+
+    let signed = |gas_price| {
+        sign(gas_price)
+            .map(|tx| tx.map(|tx| tx.raw().expect("offline signing yields raw transactions")))
+    };
 
-    let mut uniswap_pair_bases_addrs = HashSet::new();
-    let mut uniswap_pair_bases: Vec<_> = pairs
-        .iter()
-        .unique_by(|pair| pair.uniswap_pair)
-        .map(|pair| {
-            uniswap_pair_bases_addrs.insert(pair.uniswap_pair);
-            UniswapPairBase {
-                contract: UniswapPair::at(&web3, pair.uniswap_pair),
-                address: pair.uniswap_pair,
-                token0: pair.token0,
-            }
-        })
-        .collect();
+    let tx = match signed(min_gas_price).await {
+        Ok(tx) => tx,
+        Err(err) => {
+            log::warn!(
+                "{} Failed signing execution: {}",
+                format_block_number(number),
+                err
+            );
+            return Sent::Nothing;
+        }
+    };
 
-    for (_, token) in tokens.iter() {
-        if let Some(weth_uniswap_pair) = token.weth_uniswap_pair {
-            if !uniswap_pair_bases_addrs.contains(&weth_uniswap_pair) {
-                let contract = UniswapPair::at(&web3, weth_uniswap_pair);
-                uniswap_pair_bases.push(UniswapPairBase {
-                    token0: contract.token_0().call().await.unwrap(),
-                    address: weth_uniswap_pair,
-                    contract,
-                });
+    let simulated = match relay.simulate(&[tx], target).await {
+        Ok(simulation) => simulation.results.into_iter().next(),
+        Err(err) => {
+            metrics::RPC_ERRORS
+                .with_label_values(&["relay_simulate"])
+                .inc();
+            log::warn!(
+                "{} Failed simulating bundle: {}",
+                format_block_number(number),
+                err
+            );
+            return Sent::Nothing;
+        }
+    };
+
+    let gas_used = match simulated {
+        Some(simulated) if simulated.error.is_none() && simulated.revert.is_none() => {
+            simulated.gas_used
+        }
+        Some(simulated) => {
+            log::info!(
+                "{} Simulated bundle reverted: {:?}",
+                format_block_number(number),
+                Revert::from_reason(simulated.revert.or(simulated.error))
+            );
+            return Sent::Nothing;
+        }
+        None => {
+            log::warn!(
+                "{} Relay simulated an empty bundle",
+                format_block_number(number)
+            );
+            return Sent::Nothing;
+        }
+    };
+
+    let gas_price = bid(gas_used);
+    let tx: Bytes = match signed(gas_price).await {
+        Ok(tx) => tx,
+        Err(err) => {
+            log::warn!(
+                "{} Failed signing execution: {}",
+                format_block_number(number),
+                err
+            );
+            return Sent::Nothing;
+        }
+    };
+    let hash = H256(keccak256(&tx.0));
+
+    for block_number in target.as_u64()..=last_target.as_u64() {
+        if let Err(err) = relay
+            .send(std::slice::from_ref(&tx), block_number.into())
+            .await
+        {
+            metrics::RPC_ERRORS.with_label_values(&["relay_send"]).inc();
+            log::warn!(
+                "{} Failed sending bundle for {}: {}",
+                format_block_number(number),
+                block_number,
+                err
+            );
+        }
+    }
+
+    log::info!(
+        "{} Sent bundle bidding {} gwei for {} gas, transaction hash {:?}",
+        format_block_number(number),
+        gas_price / U256::exp10(9),
+        gas_used,
+        hash
+    );
+
+    let eth = arbrito.raw_instance().web3().eth();
+    loop {
+        let (head, receipt) = tokio::join!(eth.block_number(), eth.transaction_receipt(hash));
+
+        match receipt {
+            Ok(Some(receipt)) => {
+                return Sent::Mined {
+                    receipt: Box::new(receipt),
+                    gas_price,
+                    replacement: 0,
+                }
+            }
+            Ok(None) if head.as_ref().is_ok_and(|head| *head >= last_target) => {
+                log::info!(
+                    "{} {}",
+                    format_block_number(number),
+                    "Bundle wasn't included".red().dimmed(),
+                );
+                return Sent::NotIncluded;
+            }
+            Ok(None) => tokio::time::delay_for(std::time::Duration::from_secs(1)).await,
+            Err(err) => {
+                metrics::RPC_ERRORS.with_label_values(&["receipt"]).inc();
+                log::warn!(
+                    "{} Failed fetching bundle receipt: {}",
+                    format_block_number(number),
+                    err
+                );
+                return Sent::Unknown;
             }
         }
     }
+}
+
+/// The gas strategy of the settings, scaling the gas price by the given
+/// scales when it does.
+fn gas_strategy(settings: &Settings, gas_scales: GasScales) -> Arc<dyn GasStrategy> {
+    match settings.gas_strategy.as_str() {
+        "scaled" => Arc::new(ScaledGasPrice {
+            min_scale: gas_scales.min,
+            max_scale: gas_scales.max,
+        }),
+        "profit_share" => Arc::new(ProfitShare {
+            percent: settings.gas_profit_share_percent,
+        }),
+        "percentile" => Arc::new(Percentile {
+            percentile: settings.gas_percentile,
+        }),
+        strategy => panic!("unknown gas strategy {}", strategy),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+
+    let settings = Settings::load(std::env::args().skip(1)).expect("failed loading settings");
+
+    let web3_ipc_path = std::env::var("WEB3_IPC_PATH").expect("where's the ipc");
+    let web3 = Web3::new(Ipc::new(web3_ipc_path).await.expect("ipc failed"));
+
+    let network = Networks::select(&settings.network).expect("failed selecting network");
+    network.check_chain_id(&web3).await;
+
+    let Pairs { tokens, pairs } = Pairs::read().expect("pairs reading failed");
 
-    let balancer_pair_bases: Vec<_> = pairs
+    let token_addresses: Vec<_> = tokens.iter().map(|t| t.address).collect();
+    let decimals = fetch_decimals(&web3, &token_addresses).await;
+
+    let tokens: Arc<HashMap<_, _>> = Arc::new(
+        tokens
+            .into_iter()
+            .map(|mut t| {
+                // Falls back to the decimals the pairs file was fetched with.
+                t.decimals = decimals.get(&t.address).copied().unwrap_or(t.decimals);
+                (t.address, t)
+            })
+            .collect(),
+    );
+
+    let uniswap_pair_addresses: HashSet<_> = pairs
+        .iter()
+        .map(|pair| pair.uniswap_pair)
+        .chain(tokens.values().filter_map(|token| token.weth_uniswap_pair))
+        .collect();
+
+    let futs = uniswap_pair_addresses.into_iter().map(|address| {
+        let contract = UniswapPair::at(&web3, address);
+        async move {
+            let metadata = UniswapPairMetadata::fetch(&contract).await;
+            let base = UniswapPairBase {
+                contract,
+                address,
+                metadata,
+            };
+            (address, base)
+        }
+    });
+
+    let uniswap_pair_bases: HashMap<_, _> = join_all(futs).await.into_iter().collect();
+
+    let number = web3
+        .eth()
+        .block_number()
+        .await
+        .expect("failed fetching block number");
+    let block_id = BlockId::Number(BlockNumber::Number(number));
+
+    let futs: Vec<_> = pairs
         .iter()
         .group_by(|pair| pair.balancer_pool)
         .into_iter()
         .map(|(address, pairs)| {
-            let tokens = pairs
+            let tokens: HashSet<_> = pairs
                 .into_iter()
                 .flat_map(|pair| vec![pair.token0, pair.token1])
                 .collect();
 
-            BalancerPoolBase {
-                contract: BalancerPool::at(&web3, address),
-                address,
-                tokens,
+            let contract = BalancerPool::at(&web3, address);
+            async move {
+                let metadata = BalancerPoolMetadata::fetch(&contract, &tokens, block_id)
+                    .await
+                    .expect("failed fetching balancer pool metadata");
+                let base = BalancerPoolBase {
+                    contract,
+                    address,
+                    tokens,
+                    metadata,
+                };
+                (address, base)
             }
         })
         .collect();
 
-    let weth_address = H160::from_str(WETH_ADDRESS).expect("failed parsing weth address");
+    let mut balancer_pool_bases: HashMap<_, _> = join_all(futs).await.into_iter().collect();
+
+    let weth_address = settings.weth_address.unwrap_or(network.wrapped_native);
     let weth = tokens.get(&weth_address).expect("where's my weth, boy?");
 
-    let arbrito_address = H160::from_str(ARBRITO_ADDRESS).expect("failed parsing arbrito address");
-    let arbrito = Arbrito::at(&web3, arbrito_address);
+    let arbrito_address = settings
+        .arbrito_address
+        .or(network.arbrito_address)
+        .expect("no arbrito deployment on network");
+    let replay = settings
+        .replay_path
+        .as_ref()
+        .map(|path| recording::read(path).expect("failed reading recording"));
+    let mock = replay.as_ref().map(|_| MockTransport::new());
+    let arbrito = match &mock {
+        Some(mock) => Arbrito::at(&Web3::new(mock.clone()), arbrito_address),
+        None => Arbrito::at(&web3, arbrito_address),
+    };
+
+    if let Some(address) = &settings.metrics_address {
+        let bound = metrics::serve(address).expect("failed serving metrics");
+        log::info!("Serving metrics at http://{}/metrics", bound);
+    }
+
+    let mut journal = settings
+        .journal_path
+        .as_ref()
+        .map(|path| Journal::open(path).expect("failed opening journal"));
+
+    let mut recorder = settings
+        .record_path
+        .as_ref()
+        .map(|path| Recorder::open(path).expect("failed opening recording"));
+
+    let routers = network.routers();
+
+    let mut competitors = Competitors::read().unwrap_or_else(|err| {
+        log::warn!("Starting without registered competitors: {}", err);
+        Competitors::default()
+    });
+    let mut learner = Learner::new(COMPETITOR_LEARN_THRESHOLD);
+
+    let mut gas_scales = GasScales {
+        min: settings.min_gas_scale,
+        max: settings.max_gas_scale,
+    };
+    let control = Control::new(gas_scales);
+    if let Some(address) = &settings.admin_address {
+        let bound = admin::serve(address, control.clone()).expect("failed serving admin api");
+        log::info!("Serving admin api at http://{}", bound);
+    }
 
-    let executor_address =
-        H160::from_str(EXECUTOR_ADDRESS).expect("failed parsing executor address");
+    let mut dashboard = if settings.dashboard {
+        let names = dashboard::Names::new(&tokens, &pairs);
+        Some(Dashboard::open(names).expect("failed opening dashboard"))
+    } else {
+        None
+    };
 
-    let uniswap_router_address =
-        H160::from_str(UNISWAP_ROUTER_ADDRESS).expect("failed parsing uniswap router address");
+    let mut config = Config {
+        expected_gas_usage: U256::from(settings.expected_gas_usage),
+        max_gas_usage: U256::from(settings.max_gas_usage),
+        min_gas_scale: gas_scales.min,
+    };
 
-    let config = Config {
-        expected_gas_usage: U256::from(EXPECTED_GAS_USAGE),
-        max_gas_usage: U256::from(MAX_GAS_USAGE),
-        min_gas_scale: MIN_GAS_SCALE,
-        max_gas_scale: MAX_GAS_SCALE,
+    let mut recent_gas_prices = RecentGasPrices::new(RECENT_GAS_PRICE_BLOCKS);
+    let mut gas = Gas {
+        strategy: gas_strategy(&settings, gas_scales),
+        recent_gas_prices: Arc::new(vec![]),
+        pending_txs: Arc::new(vec![]),
     };
 
     let execution_lock = Arc::new(Mutex::new(()));
     let (execution_tx, execution_rx) = mpsc::unbounded_channel();
     let (pending_txs_tx, pending_txs_rx) = mpsc::unbounded_channel();
+    let (transactions_tx, transactions_rx) = mpsc::unbounded_channel();
+
+    let (paper_trades_tx, mut paper_trades_rx) = mpsc::unbounded_channel();
+    let mut paper_trades: Vec<PaperTrade> = vec![];
+    let mut paper_earnings = U256::zero();
+    let mut paper_gas = U256::zero();
+    let simulator = arbrito.clone();
+
+    let signers: Vec<_> = settings
+        .keystore_paths
+        .iter()
+        .map(|path| {
+            let password = std::env::var("ARBRITO_EXEC_PASSWORD").expect("where's the password");
+            Signer::from_keystore(path, &password, network.chain_id)
+        })
+        .chain(
+            settings
+                .key_paths
+                .iter()
+                .map(|path| Signer::from_key_file(path, network.chain_id)),
+        )
+        .map(|signer| signer.expect("failed loading executor key"))
+        .collect();
+
+    let submission = match &settings.relay_url {
+        _ if settings.dry_run => Submission::Paper(paper_trades_tx),
+        _ if signers.is_empty() => panic!("executing needs keystore_paths or key_paths"),
+        Some(url) => Submission::Bundle {
+            relay: Relay::new(url.clone(), signers[0].key().clone()),
+            bid_percent: settings.relay_bid_percent,
+        },
+        None => Submission::Public,
+    };
+
+    let addresses = if signers.is_empty() {
+        vec![settings.executor_address]
+    } else {
+        signers.iter().map(Signer::address).collect()
+    };
+    let executor_address = addresses[0];
+
+    let accounts = Accounts {
+        pool: Arc::new(StdMutex::new(AccountPool::new(addresses, DROPPED_TX_TTL))),
+        signers: Arc::new(
+            signers
+                .into_iter()
+                .map(|signer| (signer.address(), signer))
+                .collect(),
+        ),
+    };
+
+    let (outcomes_tx, mut outcomes_rx) = mpsc::unbounded_channel();
+    let mut ledger = match &settings.ledger_path {
+        Some(path) if std::path::Path::new(path).exists() => {
+            Ledger::new(&ledger::read(path).expect("failed reading ledger"))
+        }
+        _ => Ledger::default(),
+    };
 
     tokio::spawn(executor(
-        arbrito.clone(),
-        executor_address,
+        Executor {
+            arbrito: arbrito.clone(),
+            accounts: accounts.clone(),
+            submission,
+            outcomes_tx,
+            control: control.clone(),
+        },
         execution_lock.clone(),
         pending_txs_rx,
         execution_rx,
     ));
 
     let balancer_pools: HashSet<H160> = pairs.iter().map(|p| p.balancer_pool).collect();
+    let uniswap_pairs: HashSet<H160> = uniswap_pair_bases.keys().copied().collect();
+
+    let pair_index: HashMap<_, _> = uniswap_pair_bases
+        .values()
+        .flat_map(|pair| {
+            let UniswapPairMetadata { token0, token1 } = pair.metadata;
+            vec![
+                ((token0, token1), pair.address),
+                ((token1, token0), pair.address),
+            ]
+        })
+        .collect();
 
     let arbritage_pairs: Vec<_> = pairs
         .into_iter()
@@ -586,63 +1483,508 @@ async fn main() {
         })
         .collect();
 
-    let web32 = web3.clone();
-    let tokens2 = tokens.clone();
-    tokio::spawn(
-        web3.eth_subscribe()
-            .subscribe_new_pending_transactions()
-            .await
-            .expect("failed subscribing to new pending transactions")
-            .filter_map(|res| async move { Result::ok(res) })
-            .filter_map(move |tx_hash| {
-                web32
-                    .eth()
-                    .transaction(TransactionId::Hash(tx_hash))
-                    .map(Result::ok)
-                    .map(Option::flatten)
+    if replay.is_none() {
+        let web32 = web3.clone();
+        tokio::spawn(
+            web3.eth_subscribe()
+                .subscribe_new_pending_transactions()
+                .await
+                .expect("failed subscribing to new pending transactions")
+                .filter_map(|res| async move { counted("pending_txs", res) })
+                .filter_map(move |tx_hash| {
+                    web32
+                        .eth()
+                        .transaction(TransactionId::Hash(tx_hash))
+                        .map(|res| counted("pending_tx", res))
+                        .map(Option::flatten)
+                })
+                .for_each(move |tx: web3::types::Transaction| {
+                    transactions_tx.send(tx).expect("Transactions rx died");
+                    ready(())
+                }),
+        );
+    }
+
+    let mut state = match &replay {
+        Some(records) => records
+            .iter()
+            .find_map(|record| match &record.event {
+                Event::Snapshot(state) => Some(state.as_ref().clone()),
+                _ => None,
             })
-            .for_each(move |tx: web3::types::Transaction| {
-                if let Some(swap) = PendingTx::from_transaction(
+            .expect("recording without a state snapshot"),
+        None => {
+            let (state, block) = tokio::join!(
+                StateCache::snapshot(&uniswap_pair_bases, &balancer_pool_bases, number),
+                web3.eth().block(block_id),
+            );
+            let state = StateCache {
+                hash: block
+                    .expect("failed fetching block")
+                    .and_then(|block| block.hash)
+                    .unwrap_or_default(),
+                ..state.expect("failed snapshotting state")
+            };
+            record(&mut recorder, &Event::Snapshot(Box::new(state.clone())));
+            state
+        }
+    };
+
+    let mut last_block: Option<Block> = None;
+    let mut mempool = Mempool::new(PENDING_TX_TTL);
+
+    let mut events = match replay {
+        Some(records) => recording::replay(records, settings.replay_speed).boxed(),
+        None => futures::stream::select(
+            LatestBlock::new(
+                web3.clone(),
+                accounts.pool().addresses(),
+                state.markets(),
+                state.number,
+            )
+            .map(|head| Event::NewHead(Box::new(head))),
+            transactions_rx.map(|tx| Event::PendingTx(Box::new(tx))),
+        )
+        .boxed(),
+    };
+
+    // Executions in flight and their conflicts show up in between blocks.
+    let mut redraws = tokio::time::interval(DASHBOARD_REDRAW_INTERVAL);
+
+    loop {
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = redraws.tick(), if dashboard.is_some() => {
+                redraw(&mut dashboard, &control);
+                continue;
+            }
+        };
+
+        // Heads are recorded once the state is up to date with them.
+        if !matches!(event, Event::NewHead(_)) {
+            record(&mut recorder, &event);
+        }
+
+        let head = match event {
+            Event::Snapshot(snapshot) => {
+                state = *snapshot;
+                continue;
+            }
+            Event::NewHead(head) => head,
+            Event::PendingTx(tx) => {
+                let pending_tx = match PendingTx::from_transaction(
                     &tx,
-                    uniswap_router_address,
+                    &routers,
+                    &competitors,
+                    &uniswap_pairs,
                     &balancer_pools,
-                    &tokens2,
+                    &tokens,
                 ) {
-                    log::debug!("Possible conflicting swap {:?} {:?}", swap, tx.hash);
-                    pending_txs_tx.send(swap).expect("Pending txs rx died");
+                    Some(pending_tx) => pending_tx,
+                    None => continue,
+                };
+
+                log::debug!("Possible conflicting swap {:?} {:?}", pending_tx, tx.hash);
+                if log::log_enabled!(target: "pending_txs", log::Level::Debug) {
+                    match serde_json::to_string(&pending_tx) {
+                        Ok(json) => log::debug!(target: "pending_txs", "{}", json),
+                        Err(err) => log::warn!("Failed serializing pending tx: {}", err),
+                    }
                 }
+                let seen = last_block.map_or_else(U64::zero, |b| b.number);
+                match mempool.insert(pending_tx.clone(), seen) {
+                    Insertion::New => metrics::MEMPOOL_TXS.with_label_values(&["new"]).inc(),
+                    Insertion::Replaced(previous) => {
+                        metrics::MEMPOOL_TXS.with_label_values(&["replaced"]).inc();
+                        log::debug!(
+                            "{} Pending tx {:?} replaced {:?} ({} -> {} gwei)",
+                            format_block_number(seen),
+                            pending_tx.hash,
+                            previous.hash,
+                            previous.gas_price / U256::exp10(9),
+                            pending_tx.gas_price / U256::exp10(9),
+                        )
+                    }
+                    Insertion::Underpriced => {
+                        metrics::MEMPOOL_TXS
+                            .with_label_values(&["underpriced"])
+                            .inc();
+                        continue;
+                    }
+                    Insertion::Duplicate => {
+                        metrics::MEMPOOL_TXS.with_label_values(&["duplicate"]).inc();
+                        continue;
+                    }
+                }
+                metrics::MEMPOOL_SIZE.set(mempool.len() as i64);
+
+                // Only what may be mined ahead of the executions conflicts.
+                pending_txs_tx
+                    .send(pending_tx.clone())
+                    .expect("Pending txs rx died");
+
+                let block = match last_block {
+                    Some(block)
+                        if settings.backrun
+                            && !control.paused()
+                            && execution_lock.try_lock().is_ok() =>
+                    {
+                        block
+                    }
+                    _ => continue,
+                };
 
-                ready(())
-            }),
-    );
+                // Small swaps don't move prices enough to be worth the attempts.
+                let impact = pending_tx.impact(&state.pairs, &state.pools, &pair_index);
+                if impact < settings.backrun_min_impact_bps {
+                    continue;
+                }
+
+                let context = Context {
+                    pools: state.pools.clone(),
+                    pairs: state.pairs.clone(),
+                    config,
+                    gas: gas.clone(),
+                    block,
+                }
+                .project(mempool.txs(), pending_tx.gas_price, &pair_index);
+
+                let max_attempt =
+                    tradeable_attempts(&arbritage_pairs, &balancer_pool_bases, &context)
+                        .into_iter()
+                        .filter(|attempt| control.enabled(attempt.pair.id()))
+                        .filter_map(|attempt| attempt.backrun(pending_tx.gas_price))
+                        .max_by(|a1, a2| a1.result.cmp(&a2.result));
+
+                if let Some(attempt) = max_attempt {
+                    if let ArbritageResult::NetProfit {
+                        weth_profit,
+                        amount,
+                        ..
+                    } = attempt.result
+                    {
+                        log::info!(
+                            "{} {}: borrow {} for {} profit ({} @ {} gwei) after {:?}",
+                            format_block_number(block.number),
+                            "Backrunning pending swap".bold().underline(),
+                            format_amount(&attempt.tokens.0, amount),
+                            attempt.tokens.1.symbol,
+                            format_amount_colored(weth, weth_profit),
+                            pending_tx.gas_price / U256::exp10(9),
+                            pending_tx.hash,
+                        );
+                    }
 
-    let mut latest_block = LatestBlock::new(web3.clone(), executor_address);
+                    if let Some(journal) = &mut journal {
+                        if let Err(err) =
+                            journal.record(&[attempt.journal_entry(true, unix_time())])
+                        {
+                            log::warn!("Failed journaling backrun: {}", err);
+                        }
+                    }
+
+                    if execution_tx.send((vec![attempt], context)).is_err() {
+                        panic!("where's my executor at?");
+                    }
+                }
+
+                continue;
+            }
+        };
+
+        let number = head.number;
+        log::info!("{} New block header", format_block_number(number));
+        if let Some(mock) = &mock {
+            mock.mine(number);
+        }
+
+        // Replays take the snapshot recorded ahead of the head instead.
+        let mut snapshotted = false;
+        let mut follows = state.update(&head);
+        if !follows && mock.is_none() {
+            log::warn!(
+                "{} Head isn't on the cached chain, snapshotting it again",
+                format_block_number(number)
+            );
+            match StateCache::snapshot(&uniswap_pair_bases, &balancer_pool_bases, number).await {
+                Ok(snapshot) => {
+                    state = StateCache {
+                        hash: head.hash,
+                        stale_pools: std::mem::take(&mut state.stale_pools),
+                        ..snapshot
+                    };
+                    snapshotted = true;
+                    follows = true;
+                }
+                Err(err) => {
+                    // Tried again with the next head, which won't follow either.
+                    metrics::RPC_ERRORS.with_label_values(&["snapshot"]).inc();
+                    log::warn!(
+                        "{} Failed snapshotting state: {}",
+                        format_block_number(number),
+                        err
+                    );
+                }
+            }
+        }
+
+        for hash in competitors::arbitrages(&head.logs) {
+            let to = head
+                .transactions
+                .iter()
+                .find(|tx| tx.hash == hash)
+                .and_then(|tx| tx.to);
+
+            let contract = match to {
+                Some(to) if to != arbrito_address && !routers.known(to) => to,
+                _ => continue,
+            };
+
+            if learner.record(&mut competitors, contract) {
+                log::info!("Learned competitor {:?}", contract);
+                if let Err(err) = competitors.write() {
+                    log::warn!("Failed writing competitors: {}", err);
+                }
+            }
+        }
+
+        // Replays take whatever was read again from the recorded snapshots.
+        let stale_pools: HashSet<_> = if mock.is_some() {
+            state.stale_pools.clear();
+            HashSet::new()
+        } else if number.as_u64() % METADATA_REFRESH_INTERVAL == 0 {
+            state.stale_pools.clear();
+            balancer_pool_bases.keys().copied().collect()
+        } else {
+            state.stale_pools.drain().collect()
+        };
+
+        let mut refreshed = false;
+        if !stale_pools.is_empty() {
+            let block_id = BlockId::Number(BlockNumber::Number(number));
+            let futs = balancer_pool_bases
+                .values_mut()
+                .filter(|pool| stale_pools.contains(&pool.address))
+                .map(|pool| async move {
+                    pool.refresh(block_id).await?;
+                    pool.resolve(block_id).await
+                });
+
+            match try_join_all(futs).await {
+                Ok(pools) => {
+                    state.pools.extend(pools);
+                    refreshed = true;
+                    log::info!(
+                        "{} Refreshed {} stale balancer pools",
+                        format_block_number(number),
+                        stale_pools.len()
+                    );
+                }
+                Err(err) => {
+                    // Tried again with the next block.
+                    metrics::RPC_ERRORS.with_label_values(&["refresh"]).inc();
+                    state.stale_pools.extend(stale_pools);
+                    log::warn!(
+                        "{} Failed refreshing stale balancer pools: {}",
+                        format_block_number(number),
+                        err
+                    );
+                }
+            }
+        }
+
+        let mut drifts = 0;
+        if mock.is_none() && number.as_u64() % STATE_CHECKSUM_INTERVAL == 0 {
+            match state
+                .checksum(&uniswap_pair_bases, &balancer_pool_bases)
+                .await
+            {
+                Ok(found) => {
+                    drifts = found;
+                    log::info!(
+                        "{} State cache checksum found {} drifts",
+                        format_block_number(number),
+                        drifts
+                    );
+                }
+                Err(err) => {
+                    metrics::RPC_ERRORS.with_label_values(&["checksum"]).inc();
+                    log::warn!(
+                        "{} Failed checksumming state cache: {}",
+                        format_block_number(number),
+                        err
+                    );
+                }
+            }
+        }
+
+        if snapshotted || refreshed || drifts > 0 {
+            record(&mut recorder, &Event::Snapshot(Box::new(state.clone())));
+        }
+        record(&mut recorder, &Event::NewHead(head.clone()));
 
-    while let Some(block) = latest_block.next().await {
-        log::info!("{} New block header", format_block_number(block.number));
-        if let Err(_) = execution_lock.try_lock() {
+        if !follows {
+            log::info!(
+                "{} Skipping attempts on the orphaned state",
+                format_block_number(number)
+            );
+            continue;
+        }
+
+        if execution_lock.try_lock().is_err() {
             log::info!(
                 "{} Waiting on previous execution",
-                format_block_number(block.number)
+                format_block_number(number)
             );
             continue;
         };
 
         let t = std::time::Instant::now();
-        let block = Block::fetch(&web3, block.number, executor_address).await;
 
-        let futs = uniswap_pair_bases.iter().map(|pair| pair.resolve(block));
-        let uniswap_pair_resolves: HashMap<_, _> = join_all(futs).await.into_iter().collect();
+        if control.gas_scales() != gas_scales {
+            gas_scales = control.gas_scales();
+            config.min_gas_scale = gas_scales.min;
+            gas.strategy = gas_strategy(&settings, gas_scales);
+            log::info!(
+                "{} Gas scales changed to {}-{}",
+                format_block_number(number),
+                gas_scales.min,
+                gas_scales.max
+            );
+        }
 
-        let futs = balancer_pair_bases.iter().map(|pair| pair.resolve(block));
-        let balancer_pool_resolves: HashMap<_, _> = join_all(futs).await.into_iter().collect();
+        let block = Block::sync(&head, &accounts);
+        last_block = Some(block);
 
-        let context = Context {
-            pools: balancer_pool_resolves,
-            pairs: uniswap_pair_resolves,
+        recent_gas_prices.push(head.transactions.iter().map(|tx| tx.gas_price).collect());
+        mempool.prune(
+            number,
+            head.transactions.iter().map(|tx| (tx.from, tx.nonce)),
+        );
+
+        metrics::MEMPOOL_SIZE.set(mempool.len() as i64);
+
+        gas.recent_gas_prices = Arc::new(recent_gas_prices.sorted());
+        gas.pending_txs = Arc::new(mempool.txs().cloned().collect());
+
+        for outcome in std::iter::from_fn(|| outcomes_rx.try_recv().ok()) {
+            if let Some(path) = &settings.ledger_path {
+                if let Err(err) = ledger::append(path, &outcome) {
+                    log::warn!("Failed appending to ledger: {}", err);
+                }
+            }
+
+            ledger.record(&outcome);
+            let token = &tokens[&outcome.profit_token];
+            let today = &ledger.day(outcome.day())[&outcome.profit_token];
+
+            log::info!(
+                "{} Made {} over {} executions today ({} succeeded, {} on gas)",
+                format_block_number(block.number),
+                format_amount_colored(token, today.profit),
+                today.executions,
+                today.succeeded,
+                format_amount(weth, today.gas_cost),
+            );
+        }
+
+        control.set_pnl(ledger.day(unix_time() / ledger::SECONDS_PER_DAY));
+
+        paper_trades.extend(std::iter::from_fn(|| paper_trades_rx.try_recv().ok()));
+        let (due, pending): (Vec<_>, Vec<_>) = paper_trades
+            .into_iter()
+            .partition(|trade| trade.attempt.block.number < block.number);
+        paper_trades = pending;
+
+        if !due.is_empty() {
+            let settled = Context {
+                pools: state.pools.clone(),
+                pairs: state.pairs.clone(),
+                config,
+                gas: gas.clone(),
+                block,
+            };
+
+            for trade in due {
+                let PaperTrade {
+                    attempt,
+                    amount,
+                    gas_price,
+                    ctx,
+                } = trade;
+
+                let simulation = perform(&simulator, &attempt, amount, &ctx)
+                    .from(Account::Local(executor_address, None))
+                    .view()
+                    .block(BlockId::Number(BlockNumber::Number(block.number)))
+                    .call()
+                    .await;
+
+                // Reverts are charged the whole gas too, as an upper bound.
+                let gas = gas_price * config.expected_gas_usage;
+                paper_gas += gas;
+
+                match simulation {
+                    Ok(()) => {
+                        let earned = attempt
+                            .pair
+                            .realized(&attempt.tokens.0, &attempt.tokens.1, amount, &settled)
+                            .unwrap_or_default();
+                        paper_earnings += earned;
+
+                        log::info!(
+                            "{} Paper trade borrowing {} for {} would have earned {} ({} on gas)",
+                            format_block_number(attempt.block.number),
+                            format_amount(&attempt.tokens.0, amount),
+                            attempt.tokens.1.symbol,
+                            format_amount_colored(weth, earned),
+                            format_amount(weth, gas),
+                        );
+                    }
+                    Err(err) => log::info!(
+                        "{} Paper trade borrowing {} for {} would have failed ({} on gas): {}",
+                        format_block_number(attempt.block.number),
+                        format_amount(&attempt.tokens.0, amount),
+                        attempt.tokens.1.symbol,
+                        format_amount(weth, gas),
+                        err,
+                    ),
+                }
+            }
+
+            log::info!(
+                "{} Paper trading earned {} so far ({} on gas)",
+                format_block_number(block.number),
+                format_amount_colored(weth, paper_earnings),
+                format_amount(weth, paper_gas),
+            );
+        }
+
+        // Attempts are sized on the state projected with the pending txs, but
+        // performed pinned to the mined one, which is all the chain has when
+        // they are simulated and checked.
+        let mined = Context {
+            pools: state.pools.clone(),
+            pairs: state.pairs.clone(),
             config,
+            gas: gas.clone(),
             block,
         };
+        let context = Context {
+            pools: mined.pools.clone(),
+            pairs: mined.pairs.clone(),
+            config,
+            gas: gas.clone(),
+            block,
+        }
+        .project(
+            mempool.txs(),
+            block.gas_price * config.min_gas_scale,
+            &pair_index,
+        );
 
         let min_required_profit =
             block.gas_price * config.min_gas_scale * config.expected_gas_usage;
@@ -650,15 +1992,11 @@ async fn main() {
         log::info!(
             "{} Min required profit {} @ {} gwei",
             format_block_number(block.number),
-            format_amount(&weth, min_required_profit),
+            format_amount(weth, min_required_profit),
             (block.gas_price * config.min_gas_scale) / U256::exp10(9)
         );
 
-        let attempts: Vec<_> = arbritage_pairs
-            .iter()
-            .map(|pair| pair.attempts(&context))
-            .flatten()
-            .collect();
+        let attempts = tradeable_attempts(&arbritage_pairs, &balancer_pool_bases, &context);
 
         let mut not_profits_count = 0;
         let mut gross_profits_count = 0;
@@ -672,59 +2010,166 @@ async fn main() {
             }
         }
 
-        let max_attempt = attempts
+        metrics::ATTEMPTS
+            .with_label_values(&["not_profit"])
+            .inc_by(not_profits_count);
+        metrics::ATTEMPTS
+            .with_label_values(&["gross_profit"])
+            .inc_by(gross_profits_count);
+        metrics::ATTEMPTS
+            .with_label_values(&["net_profit"])
+            .inc_by(net_profits_count);
+
+        // Disabled pairs are still monitored, but never picked for execution.
+        let candidates: Vec<_> = attempts
+            .iter()
+            .map(|attempt| Candidate {
+                uniswap_pair: attempt.pair.uniswap_pair,
+                balancer_pool: attempt.pair.balancer_pool,
+                profit: match attempt.result {
+                    ArbritageResult::NetProfit { weth_profit, .. }
+                        if control.enabled(attempt.pair.id()) =>
+                    {
+                        weth_profit
+                    }
+                    _ => U256::zero(),
+                },
+            })
+            .collect();
+
+        let paused = control.paused();
+        if paused {
+            log::info!("{} Execution is paused", format_block_number(block.number));
+        }
+
+        let selected: HashSet<_> = if paused {
+            HashSet::new()
+        } else {
+            select_disjoint(&candidates).into_iter().collect()
+        };
+
+        let mut ranked: Vec<_> = attempts
+            .iter()
+            .enumerate()
+            .filter(|(_, attempt)| attempt.result != ArbritageResult::NotProfit)
+            .collect();
+        ranked.sort_by(|(_, a1), (_, a2)| a2.result.cmp(&a1.result));
+
+        let timestamp = unix_time();
+        control.set_opportunities(
+            block.number.as_u64(),
+            ranked
+                .into_iter()
+                .take(TOP_OPPORTUNITIES)
+                .map(|(index, attempt)| attempt.journal_entry(selected.contains(&index), timestamp))
+                .collect(),
+        );
+
+        let (executable, rest): (Vec<_>, Vec<_>) = attempts
             .into_iter()
-            .max_by(|a1, a2| a1.result.cmp(&a2.result))
-            .expect("empty arbritage results");
+            .enumerate()
+            .partition(|(index, _)| selected.contains(index));
+
+        let mut executable: Vec<_> = executable.into_iter().map(|(_, a)| a).collect();
+        executable.sort_by(|a1, a2| a2.result.cmp(&a1.result));
+
+        if let Some(journal) = &mut journal {
+            let entries: Vec<_> = executable
+                .iter()
+                .map(|attempt| attempt.journal_entry(true, timestamp))
+                .chain(
+                    rest.iter()
+                        .map(|(_, attempt)| attempt.journal_entry(false, timestamp)),
+                )
+                .collect();
 
-        match max_attempt.result {
-            ArbritageResult::NotProfit => {
-                log::info!("{} All attempts suck", format_block_number(block.number))
+            if let Err(err) = journal.record(&entries) {
+                log::warn!("Failed journaling attempts: {}", err);
             }
-            ArbritageResult::GrossProfit {
-                weth_profit,
-                amount,
-            } => {
-                log::info!(
+        }
+
+        if executable.is_empty() {
+            match rest
+                .into_iter()
+                .map(|(_, a)| a)
+                .max_by(|a1, a2| a1.result.cmp(&a2.result))
+            {
+                None => log::info!("{} No tradeable pairs", format_block_number(block.number)),
+                Some(ArbritageAttempt {
+                    result:
+                        ArbritageResult::GrossProfit {
+                            weth_profit,
+                            amount,
+                        },
+                    tokens,
+                    ..
+                }) => log::info!(
                     "{} Best attempt found: borrow {} for {} profit ({})",
                     format_block_number(block.number),
-                    format_amount(&max_attempt.tokens.0, amount),
-                    max_attempt.tokens.1.symbol,
-                    format_amount_colored(&weth, weth_profit),
-                );
+                    format_amount(&tokens.0, amount),
+                    tokens.1.symbol,
+                    format_amount_colored(weth, weth_profit),
+                ),
+                Some(_) => {
+                    log::info!("{} All attempts suck", format_block_number(block.number))
+                }
             }
-            ArbritageResult::NetProfit {
-                min_gas_price,
-                max_gas_price,
-                weth_profit,
-                amount,
-                ..
-            } => {
-                log::info!(
-                    "{} {}: borrow {} for {} profit ({} @ {}-{} gwei)",
-                    format_block_number(block.number),
-                    "Executing best attempt".bold().underline(),
-                    format_amount(&max_attempt.tokens.0, amount),
-                    max_attempt.tokens.1.symbol,
-                    format_amount_colored(&weth, weth_profit),
-                    min_gas_price / U256::exp10(9),
-                    max_gas_price / U256::exp10(9),
-                );
+        } else {
+            for (index, attempt) in executable.iter().enumerate() {
+                if let ArbritageResult::NetProfit {
+                    min_gas_price,
+                    max_gas_price,
+                    weth_profit,
+                    amount,
+                    ..
+                } = attempt.result
+                {
+                    log::info!(
+                        "{} {} ({}/{}): borrow {} for {} profit ({} @ {}-{} gwei)",
+                        format_block_number(block.number),
+                        "Executing attempt".bold().underline(),
+                        index + 1,
+                        executable.len(),
+                        format_amount(&attempt.tokens.0, amount),
+                        attempt.tokens.1.symbol,
+                        format_amount_colored(weth, weth_profit),
+                        min_gas_price / U256::exp10(9),
+                        max_gas_price / U256::exp10(9),
+                    );
+                }
 
-                if let Err(_) = execution_tx.send((max_attempt, context)) {
-                    panic!("where's my executor at?");
+                if let Some(gas_price) = mempool.highest_gas_price(
+                    attempt.tokens.1.address,
+                    attempt.tokens.0.address,
+                    attempt.pair.uniswap_pair,
+                    attempt.pair.balancer_pool,
+                ) {
+                    log::info!(
+                        "{} Highest competing pending tx @ {} gwei",
+                        format_block_number(block.number),
+                        gas_price / U256::exp10(9),
+                    );
                 }
             }
+
+            if execution_tx.send((executable, mined)).is_err() {
+                panic!("where's my executor at?");
+            }
         }
 
+        let elapsed = t.elapsed().as_secs_f64();
+        metrics::BLOCK_PROCESSING_SECONDS.observe(elapsed);
+
         log::info!(
             "{} Processed in {:.2} seconds ({} pairs | {} net + {} gross + {} not)",
             format_block_number(block.number),
-            t.elapsed().as_secs_f64(),
+            elapsed,
             arbritage_pairs.len(),
             net_profits_count,
             gross_profits_count,
             not_profits_count
         );
+
+        redraw(&mut dashboard, &control);
     }
 }
@@ -69,11 +69,11 @@ fn root(ri: U256, ro: U256, bi: U256, bo: U256, s: U256) -> Option<U256> {
     }
 }
 
-fn uniswap_in_given_out(ri: U256, ro: U256, amount: U256) -> U256 {
+pub fn uniswap_in_given_out(ri: U256, ro: U256, amount: U256) -> U256 {
     (amount * ri * 1000) / ((ro - amount) * 997) + 1
 }
 
-fn balancer_out_given_in(bi: U256, bo: U256, s: U256, amount: U256) -> U256 {
+pub fn balancer_out_given_in(bi: U256, bo: U256, s: U256, amount: U256) -> U256 {
     let bone = U256::exp10(18);
 
     let bmul = |a: U256, b: U256| (a * b + bone / 2) / bone;
@@ -82,6 +82,15 @@ fn balancer_out_given_in(bi: U256, bo: U256, s: U256, amount: U256) -> U256 {
     bmul(bo, bone - bdiv(bi, bi + bmul(amount, bone - s)))
 }
 
+pub fn balancer_in_given_out(bi: U256, bo: U256, s: U256, amount: U256) -> U256 {
+    let bone = U256::exp10(18);
+
+    let bmul = |a: U256, b: U256| (a * b + bone / 2) / bone;
+    let bdiv = |a: U256, b: U256| (a * bone + b / 2) / b;
+
+    bdiv(bmul(bi, bdiv(bo, bo - amount) - bone), bone - s)
+}
+
 pub fn max_profit(ri: U256, ro: U256, bi: U256, bo: U256, s: U256) -> Option<(U256, U256, U256)> {
     let borrow_amount = root(ri, ro, bi, bo, s)?;
 
@@ -113,4 +122,17 @@ mod test {
         assert_eq!(amount, U256::from(860531u128));
         assert_eq!(profit, U256::from(121209478698546u128));
     }
+
+    #[test]
+    fn balancer_in_given_out_inverts() {
+        let bo = U256::from(674650730267410526933u128);
+        let bi = U256::from(2032847980u128);
+        let s = U256::from(300000000000000u128);
+
+        let amount = U256::from(860531u128);
+        let out = balancer_out_given_in(bi, bo, s, amount);
+        let back = balancer_in_given_out(bi, bo, s, out);
+
+        assert!(back <= amount && amount - back <= U256::from(1));
+    }
 }
@@ -0,0 +1,117 @@
+use ethcontract::common::{
+    abi::{encode, Contract, Function, ParamType, Token},
+    FunctionExt,
+};
+use web3::contract::tokens::Detokenize;
+
+/// Decodes the input of a call to one of the named contract functions,
+/// matched by selector. The calldata comes from untrusted transactions, so every offset
+/// and length is checked before decoding (the decoder trusts them for its
+/// allocations and slicing) and only canonically encoded inputs are accepted.
+pub fn decode<'a>(
+    abi: &'a Contract,
+    names: &[&str],
+    input: &[u8],
+) -> Option<(&'a Function, Vec<Token>)> {
+    if input.len() < 4 || input.len() % 32 != 4 {
+        return None;
+    }
+
+    let (selector, data) = input.split_at(4);
+    let function = names
+        .iter()
+        .filter_map(|name| abi.function(name).ok())
+        .find(|f| f.selector() == *selector)?;
+
+    let mut budget = data.len() / 32;
+    let mut offset = 0;
+    for param in &function.inputs {
+        offset = check(&param.kind, data, offset, &mut budget)?;
+    }
+
+    let tokens = function.decode_input(data).ok()?;
+    if encode(&tokens) != data {
+        return None;
+    }
+
+    Some((function, tokens))
+}
+
+/// Converts decoded arguments into the given typed tuple.
+pub fn args<T: Detokenize>(tokens: Vec<Token>) -> Option<T> {
+    T::from_tokens(tokens).ok()
+}
+
+/// Converts a decoded tuple argument, such as a struct, into the given typed tuple.
+pub fn tuple<T: Detokenize>(token: Token) -> Option<T> {
+    match token {
+        Token::Tuple(fields) => args(fields),
+        _ => None,
+    }
+}
+
+fn pointer(data: &[u8], index: usize) -> Option<usize> {
+    let word = data.get(32 * index..32 * (index + 1))?;
+    if word[..28].iter().any(|b| *b != 0) {
+        return None;
+    }
+
+    Some(u32::from_be_bytes([word[28], word[29], word[30], word[31]]) as usize)
+}
+
+/// Walks the parameter the same way the decoder does, returning the offset
+/// of the next one. Array lengths are charged against a budget of words, so
+/// that overlapping arrays can't make the decoder produce more tokens than
+/// the calldata could canonically hold.
+fn check(kind: &ParamType, data: &[u8], offset: usize, budget: &mut usize) -> Option<usize> {
+    let words = data.len() / 32;
+
+    match kind {
+        ParamType::Address | ParamType::Int(_) | ParamType::Uint(_) | ParamType::Bool => {
+            Some(offset + 1).filter(|&next| next <= words)
+        }
+        ParamType::FixedBytes(len) => Some(offset + len.div_ceil(32)).filter(|&next| next <= words),
+        ParamType::Bytes | ParamType::String => {
+            let start = pointer(data, offset)? / 32;
+            let len = pointer(data, start)?;
+            if len.div_ceil(32) > words - start - 1 {
+                return None;
+            }
+
+            Some(offset + 1)
+        }
+        ParamType::Array(kind) => {
+            let start = pointer(data, offset)? / 32;
+            let len = pointer(data, start)?;
+            *budget = budget.checked_sub(len)?;
+
+            let tail = &data[32 * (start + 1)..];
+            (0..len).try_fold(0, |next, _| check(kind, tail, next, budget))?;
+
+            Some(offset + 1)
+        }
+        ParamType::FixedArray(inner, len) => {
+            let kinds = std::iter::repeat_n(&**inner, *len);
+            check_sequence(kinds, kind.is_dynamic(), data, offset, budget)
+        }
+        ParamType::Tuple(kinds) => {
+            check_sequence(kinds.iter(), kind.is_dynamic(), data, offset, budget)
+        }
+    }
+}
+
+fn check_sequence<'a>(
+    mut kinds: impl Iterator<Item = &'a ParamType>,
+    dynamic: bool,
+    data: &[u8],
+    offset: usize,
+    budget: &mut usize,
+) -> Option<usize> {
+    if dynamic {
+        let tail = data.get(32 * (pointer(data, offset)? / 32)..)?;
+        kinds.try_fold(0, |next, kind| check(kind, tail, next, budget))?;
+        Some(offset + 1)
+    } else {
+        kinds.try_fold(offset, |next, kind| check(kind, data, next, budget))
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+use web3::types::{Log, Transaction, H160, H256};
+
+const FILE_PATH: &str = "competitors.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Competitor {
+    pub address: H160,
+    pub name: String,
+    /// Hex selectors their calls must match, with `?` matching any nibble.
+    /// Every call matches when there are none.
+    #[serde(default)]
+    pub selectors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Competitors {
+    pub competitors: Vec<Competitor>,
+}
+
+impl Competitor {
+    fn matches(&self, input: &[u8]) -> bool {
+        if self.selectors.is_empty() {
+            return true;
+        }
+
+        let selector = match input.get(0..4) {
+            Some(selector) => selector,
+            None => return false,
+        };
+
+        let nibbles: Vec<_> = selector
+            .iter()
+            .flat_map(|b| vec![b >> 4, b & 0xf])
+            .collect();
+        self.selectors.iter().any(|pattern| {
+            pattern.len() == 8
+                && pattern
+                    .chars()
+                    .zip(&nibbles)
+                    .all(|(c, &nibble)| c == '?' || c.to_digit(16) == Some(u32::from(nibble)))
+        })
+    }
+}
+
+impl Competitors {
+    pub fn read() -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(FILE_PATH)?;
+        let competitors = toml::from_slice(&bytes)?;
+        Ok(competitors)
+    }
+
+    pub fn write(&self) -> Result<(), Box<dyn Error>> {
+        let string = toml::to_string(self)?;
+        std::fs::write(FILE_PATH, string)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, address: H160) -> bool {
+        self.competitors.iter().any(|c| c.address == address)
+    }
+
+    /// The competitor sending or being called by the transaction, if any.
+    pub fn identify(&self, tx: &Transaction) -> Option<&Competitor> {
+        self.competitors.iter().find(|competitor| {
+            (tx.to == Some(competitor.address) || tx.from == competitor.address)
+                && competitor.matches(&tx.input.0)
+        })
+    }
+}
+
+/// Transactions whose logs touched more than one of the given markets,
+/// which is what landing an arbitrage between them looks like.
+pub fn arbitrages(logs: &[Log]) -> Vec<H256> {
+    let mut markets: HashMap<H256, HashSet<H160>> = HashMap::new();
+    for log in logs {
+        if let Some(hash) = log.transaction_hash {
+            markets.entry(hash).or_default().insert(log.address);
+        }
+    }
+
+    markets
+        .into_iter()
+        .filter(|(_, markets)| markets.len() > 1)
+        .map(|(hash, _)| hash)
+        .collect()
+}
+
+/// Registers contracts once they have landed enough arbitrages on our markets.
+pub struct Learner {
+    threshold: usize,
+    landed: HashMap<H160, usize>,
+}
+
+impl Learner {
+    pub fn new(threshold: usize) -> Learner {
+        Learner {
+            landed: HashMap::new(),
+            threshold,
+        }
+    }
+
+    /// Records an arbitrage landed by the contract, returning whether it was
+    /// registered because of it.
+    pub fn record(&mut self, competitors: &mut Competitors, contract: H160) -> bool {
+        if competitors.contains(contract) {
+            return false;
+        }
+
+        let landed = self.landed.entry(contract).or_insert(0);
+        *landed += 1;
+
+        if *landed < self.threshold {
+            return false;
+        }
+
+        self.landed.remove(&contract);
+        competitors.competitors.push(Competitor {
+            name: format!("learned-{:?}", contract),
+            selectors: vec![],
+            address: contract,
+        });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_selector_patterns() {
+        let competitor = Competitor {
+            address: H160::zero(),
+            name: "wesker".to_owned(),
+            selectors: vec!["0?0?191c".to_owned(), "0302e892".to_owned()],
+        };
+
+        assert!(competitor.matches(&[0x03, 0x03, 0x19, 0x1c, 0xff]));
+        assert!(competitor.matches(&[0x00, 0x02, 0x19, 0x1c]));
+        assert!(competitor.matches(&[0x03, 0x02, 0xe8, 0x92]));
+        assert!(!competitor.matches(&[0x00, 0x02, 0xe8, 0x92]));
+        assert!(!competitor.matches(&[0x10, 0x02, 0x19, 0x1c]));
+        assert!(!competitor.matches(&[0x03, 0x03, 0x19]));
+
+        let mut competitors = Competitors::default();
+        let mut learner = Learner::new(2);
+        let contract = H160::from_low_u64_be(1);
+
+        assert!(!learner.record(&mut competitors, contract));
+        assert!(learner.record(&mut competitors, contract));
+        assert!(!learner.record(&mut competitors, contract));
+        assert!(competitors.contains(contract));
+    }
+
+    #[test]
+    fn parses_registry() {
+        let competitors: Competitors = toml::from_str(include_str!("../competitors.toml")).unwrap();
+        let wesker = competitors.competitors[0].address;
+        assert!(competitors.contains(wesker));
+
+        let mut tx = Transaction {
+            to: Some(wesker),
+            input: vec![0x01, 0x02, 0xe8, 0x92].into(),
+            ..Transaction::default()
+        };
+        assert_eq!(competitors.identify(&tx).unwrap().name, "wesker");
+
+        tx.input = vec![0x01, 0x03, 0xe8, 0x92].into();
+        assert!(competitors.identify(&tx).is_none());
+    }
+}
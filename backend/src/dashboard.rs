@@ -0,0 +1,432 @@
+use crate::{
+    admin::{PairId, Status},
+    journal::{AttemptResult, Entry},
+    Pair, Token,
+};
+use crossterm::{
+    cursor::Show,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Stdout},
+};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use web3::types::{H160, U256};
+
+/// Names of the tokens and pairs the dashboard shows.
+pub struct Names {
+    tokens: HashMap<H160, Token>,
+    pairs: HashMap<PairId, String>,
+}
+
+impl Names {
+    pub fn new(tokens: &HashMap<H160, Token>, pairs: &[Pair]) -> Names {
+        let symbol = |address| tokens.get(address).map_or("?", |t: &Token| &t.symbol);
+        let pairs = pairs
+            .iter()
+            .map(|pair| {
+                let id = PairId {
+                    uniswap_pair: pair.uniswap_pair,
+                    balancer_pool: pair.balancer_pool,
+                };
+                (
+                    id,
+                    format!("{}/{}", symbol(&pair.token0), symbol(&pair.token1)),
+                )
+            })
+            .collect();
+
+        Names {
+            tokens: tokens.clone(),
+            pairs,
+        }
+    }
+
+    fn pair(&self, pair: &PairId) -> String {
+        self.pairs
+            .get(pair)
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", pair.uniswap_pair))
+    }
+
+    fn token(&self, address: &H160, amount: U256) -> String {
+        match self.tokens.get(address) {
+            Some(token) => format!("{} {}", token.symbol, decimal(amount, token.decimals)),
+            None => format!("{} of {:?}", amount, address),
+        }
+    }
+}
+
+/// The amount with four of its decimals.
+fn decimal(amount: U256, decimals: usize) -> String {
+    let unit = U256::exp10(decimals);
+    let fraction = (amount % unit) * U256::from(10_000) / unit;
+    format!("{}.{:04}", amount / unit, fraction.as_u64())
+}
+
+fn gwei(amount: U256) -> U256 {
+    amount / U256::exp10(9)
+}
+
+/// Colored like the profits logged.
+fn profit_style(weth_profit: U256) -> Style {
+    let style = Style::default();
+    if weth_profit >= U256::exp10(18) {
+        style
+            .fg(Color::LightGreen)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+    } else if weth_profit >= U256::exp10(17) {
+        style.fg(Color::LightGreen).add_modifier(Modifier::BOLD)
+    } else if weth_profit >= U256::exp10(16) {
+        style.fg(Color::Green)
+    } else if weth_profit >= U256::exp10(15) {
+        style.fg(Color::Yellow)
+    } else {
+        style.add_modifier(Modifier::DIM)
+    }
+}
+
+fn titled(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn header(status: &Status) -> Paragraph<'static> {
+    let mut spans = vec![
+        Span::styled(
+            status
+                .block_number
+                .map_or("Waiting for a block".to_owned(), |number| {
+                    format!("#{}", number)
+                }),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            " | gas scales {}-{} | {} disabled pairs | ",
+            status.gas_scales.min,
+            status.gas_scales.max,
+            status.disabled_pairs.len()
+        )),
+    ];
+    spans.push(if status.paused {
+        Span::styled("paused", Style::default().fg(Color::Red))
+    } else {
+        Span::styled("running", Style::default().fg(Color::Green))
+    });
+    spans.push(Span::raw(" | q to quit"));
+
+    Paragraph::new(Spans::from(spans)).block(titled("watch_pairs"))
+}
+
+fn opportunity(names: &Names, entry: &Entry) -> Row<'static> {
+    let pair = PairId {
+        uniswap_pair: entry.uniswap_pair,
+        balancer_pool: entry.balancer_pool,
+    };
+    let result = match entry.result {
+        AttemptResult::NotProfit => "not",
+        AttemptResult::GrossProfit => "gross",
+        AttemptResult::NetProfit => "net",
+    };
+    let gas = match (entry.min_gas_price, entry.max_gas_price) {
+        (Some(min), Some(max)) => format!("{}-{} gwei", gwei(min), gwei(max)),
+        _ => "-".to_owned(),
+    };
+
+    let row = Row::new(vec![
+        names.pair(&pair),
+        result.to_owned(),
+        names.token(&entry.borrow_token, entry.amount),
+        format!("WETH {}", decimal(entry.weth_profit, 18)),
+        gas,
+        if entry.executed { "yes" } else { "" }.to_owned(),
+    ]);
+    row.style(profit_style(entry.weth_profit))
+}
+
+fn opportunities<'a>(names: &Names, status: &Status, widths: &'a [Constraint]) -> Table<'a> {
+    Table::new(
+        status
+            .opportunities
+            .iter()
+            .map(|entry| opportunity(names, entry))
+            .collect::<Vec<_>>(),
+    )
+    .header(
+        Row::new(vec![
+            "Pair", "Result", "Borrow", "Profit", "Gas", "Executed",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .widths(widths)
+    .block(titled("Opportunities"))
+}
+
+fn executions(names: &Names, status: &Status) -> List<'static> {
+    let items: Vec<_> = status
+        .executions
+        .iter()
+        .map(|execution| {
+            let attempt = &execution.attempt;
+            let pair = PairId {
+                uniswap_pair: attempt.uniswap_pair,
+                balancer_pool: attempt.balancer_pool,
+            };
+            let bumps: Vec<_> = execution
+                .gas_prices
+                .iter()
+                .map(|gas_price| gwei(*gas_price).to_string())
+                .collect();
+
+            ListItem::new(format!(
+                "{} from {:?} for WETH {}: {}",
+                names.pair(&pair),
+                execution.executor,
+                decimal(attempt.weth_profit, 18),
+                if bumps.is_empty() {
+                    "not sent yet".to_owned()
+                } else {
+                    format!("{} gwei", bumps.join(" -> "))
+                }
+            ))
+        })
+        .collect();
+
+    List::new(items).block(titled("Executing"))
+}
+
+fn conflicts(names: &Names, status: &Status) -> List<'static> {
+    let items: Vec<_> = status
+        .conflicts
+        .iter()
+        .rev()
+        .map(|conflict| {
+            ListItem::new(format!(
+                "{} {:?} @ {} gwei by {}",
+                names.pair(&conflict.pair),
+                conflict.hash,
+                gwei(conflict.gas_price),
+                conflict.competitor.as_deref().unwrap_or("unknown"),
+            ))
+        })
+        .collect();
+
+    List::new(items).block(titled("Conflicts"))
+}
+
+fn pnl(names: &Names, status: &Status) -> List<'static> {
+    let items: Vec<_> = status
+        .pnl
+        .iter()
+        .map(|(token, entry)| {
+            ListItem::new(format!(
+                "{} over {} executions ({} succeeded, {} reverted, {} dropped), ETH {} on gas",
+                names.token(token, entry.profit),
+                entry.executions,
+                entry.succeeded,
+                entry.reverted,
+                entry.dropped,
+                decimal(entry.gas_cost, 18),
+            ))
+        })
+        .collect();
+
+    List::new(items).block(titled("Today"))
+}
+
+fn draw<B: Backend>(frame: &mut Frame<B>, names: &Names, status: &Status) {
+    let widths = [
+        Constraint::Percentage(15),
+        Constraint::Percentage(8),
+        Constraint::Percentage(25),
+        Constraint::Percentage(20),
+        Constraint::Percentage(20),
+        Constraint::Percentage(12),
+    ];
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(8),
+                Constraint::Length(6),
+                Constraint::Length(8),
+                Constraint::Length(6),
+            ]
+            .as_ref(),
+        )
+        .split(frame.size());
+
+    frame.render_widget(header(status), rows[0]);
+    frame.render_widget(opportunities(names, status, &widths), rows[1]);
+    frame.render_widget(executions(names, status), rows[2]);
+    frame.render_widget(conflicts(names, status), rows[3]);
+    frame.render_widget(pnl(names, status), rows[4]);
+}
+
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+}
+
+/// Full screen view of what watch_pairs is up to, taking over the terminal
+/// until dropped or quit with q.
+pub struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    names: Names,
+}
+
+impl Dashboard {
+    pub fn open(names: Names) -> Result<Dashboard, Box<dyn Error>> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+
+        // Raw mode swallows ctrl-c, so quitting is handled here.
+        std::thread::spawn(|| loop {
+            if let Ok(Event::Key(KeyEvent { code, modifiers })) = event::read() {
+                let ctrl_c = code == KeyCode::Char('c') && modifiers == KeyModifiers::CONTROL;
+                if code == KeyCode::Char('q') || code == KeyCode::Esc || ctrl_c {
+                    restore();
+                    std::process::exit(0);
+                }
+            }
+        });
+
+        Ok(Dashboard { terminal, names })
+    }
+
+    pub fn draw(&mut self, status: &Status) -> io::Result<()> {
+        let names = &self.names;
+        self.terminal
+            .draw(|frame| draw(frame, names, status))
+            .map(|_| ())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        admin::{Conflict, Control, GasScales},
+        ledger,
+    };
+    use tui::backend::TestBackend;
+    use web3::types::H256;
+
+    #[test]
+    fn draws_the_status() {
+        let token = |address, symbol: &str| Token {
+            address: H160::from_low_u64_be(address),
+            symbol: symbol.to_owned(),
+            decimals: 18,
+            weth_uniswap_pair: None,
+        };
+        let tokens: HashMap<_, _> = vec![token(1, "WETH"), token(2, "DAI")]
+            .into_iter()
+            .map(|token| (token.address, token))
+            .collect();
+        let pair = Pair {
+            balancer_pool: H160::from_low_u64_be(10),
+            uniswap_pair: H160::from_low_u64_be(11),
+            token0: H160::from_low_u64_be(1),
+            token1: H160::from_low_u64_be(2),
+        };
+        let names = Names::new(&tokens, &[pair]);
+        let id = PairId {
+            uniswap_pair: H160::from_low_u64_be(11),
+            balancer_pool: H160::from_low_u64_be(10),
+        };
+
+        let entry = Entry {
+            timestamp: 0,
+            block_number: 42,
+            uniswap_pair: id.uniswap_pair,
+            balancer_pool: id.balancer_pool,
+            borrow_token: H160::from_low_u64_be(2),
+            profit_token: H160::from_low_u64_be(1),
+            result: AttemptResult::NetProfit,
+            amount: U256::exp10(21),
+            weth_profit: U256::exp10(16) * 5,
+            min_gas_price: Some(U256::exp10(9) * 40),
+            max_gas_price: Some(U256::exp10(9) * 90),
+            executed: true,
+        };
+
+        let control = Control::new(GasScales { min: 2, max: 5 });
+        control.set_opportunities(42, vec![entry.clone()]);
+        control.start_execution(entry, H160::from_low_u64_be(3));
+        control.sent(id, U256::exp10(9) * 40);
+        control.sent(id, U256::exp10(9) * 45);
+        control.conflict(Conflict {
+            pair: id,
+            hash: H256::zero(),
+            gas_price: U256::exp10(9) * 44,
+            competitor: Some("rival".to_owned()),
+        });
+        control.set_pnl(
+            vec![(
+                H160::from_low_u64_be(1),
+                ledger::Entry {
+                    executions: 2,
+                    succeeded: 1,
+                    dropped: 1,
+                    profit: U256::exp10(16) * 3,
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        control.set_paused(true);
+
+        let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+        terminal
+            .draw(|frame| draw(frame, &names, &control.status()))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer
+            .content()
+            .chunks(buffer.area().width as usize)
+            .map(|line| {
+                line.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect();
+
+        assert!(screen.contains("#42"));
+        assert!(screen.contains("paused"));
+        assert!(screen.contains("WETH/DAI"));
+        assert!(screen.contains("DAI 1000.0000"));
+        assert!(screen.contains("WETH 0.0500"));
+        assert!(screen.contains("40-90 gwei"));
+        assert!(screen.contains("40 -> 45 gwei"));
+        assert!(screen.contains("@ 44 gwei by rival"));
+        assert!(
+            screen.contains("WETH 0.0300 over 2 executions (1 succeeded, 0 reverted, 1 dropped)")
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use web3::types::U256;
+
+/// What a bid is made for.
+#[derive(Debug, Clone, Copy)]
+pub struct Opportunity {
+    pub weth_profit: U256,
+    pub expected_gas_usage: U256,
+    pub max_gas_usage: U256,
+}
+
+impl Opportunity {
+    /// Gas price at which the expected gas usage takes the whole profit.
+    pub fn break_even(&self) -> U256 {
+        self.weth_profit / self.expected_gas_usage
+    }
+}
+
+/// What a bid is made against.
+#[derive(Debug, Clone, Copy)]
+pub struct Conditions<'a> {
+    /// The node's `eth_gasPrice`.
+    pub gas_price: U256,
+    /// Most the executor can spend.
+    pub balance: U256,
+    /// Gas prices of the pending transactions competing for the same markets.
+    pub competitors: &'a [U256],
+    /// Gas prices paid in recent blocks, sorted.
+    pub recent_gas_prices: &'a [U256],
+}
+
+impl Conditions<'_> {
+    /// Highest gas price the balance can pay for the given gas.
+    pub fn affordable(&self, gas: U256) -> U256 {
+        self.balance / gas
+    }
+}
+
+/// How a sent bid reacts to conflicting transactions showing up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    /// Keeps the bid as it is.
+    Never,
+    /// Outbids the conflicting transaction by the given amount.
+    Outbid(U256),
+}
+
+impl Bump {
+    /// The gas price to resend at given the last one and the conflicting
+    /// transaction's, if any up to the max.
+    pub fn next(self, last: U256, conflicting: U256, max: U256) -> Option<U256> {
+        match self {
+            Bump::Never => None,
+            Bump::Outbid(increment) => {
+                let next = conflicting.saturating_add(increment);
+                Some(next).filter(|next| last < *next && *next <= max)
+            }
+        }
+    }
+}
+
+/// Gas prices an execution is sent at and may be bumped up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bid {
+    pub initial: U256,
+    pub max: U256,
+    pub bump: Bump,
+}
+
+impl Bid {
+    /// Whether the bid can be made at all.
+    pub fn viable(&self) -> bool {
+        !self.initial.is_zero() && self.initial <= self.max
+    }
+}
+
+pub trait GasStrategy: Send + Sync {
+    fn bid(&self, opportunity: &Opportunity, conditions: &Conditions) -> Bid;
+}
+
+fn gwei() -> U256 {
+    U256::exp10(9)
+}
+
+/// Bids a multiple of the node's gas price, bumping over conflicting
+/// transactions up to a larger multiple.
+#[derive(Debug, Clone, Copy)]
+pub struct ScaledGasPrice {
+    pub min_scale: u8,
+    pub max_scale: u8,
+}
+
+impl GasStrategy for ScaledGasPrice {
+    fn bid(&self, opportunity: &Opportunity, conditions: &Conditions) -> Bid {
+        Bid {
+            initial: conditions.gas_price * self.min_scale,
+            max: (conditions.gas_price * self.max_scale)
+                .min(conditions.affordable(opportunity.max_gas_usage))
+                .min(opportunity.break_even()),
+            bump: Bump::Outbid(gwei()),
+        }
+    }
+}
+
+/// Bids a fixed share of the expected profit, without bumping.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfitShare {
+    pub percent: u8,
+}
+
+impl GasStrategy for ProfitShare {
+    fn bid(&self, opportunity: &Opportunity, conditions: &Conditions) -> Bid {
+        let bid = opportunity.break_even() * self.percent / 100;
+
+        Bid {
+            initial: bid,
+            max: bid.min(conditions.affordable(opportunity.max_gas_usage)),
+            bump: Bump::Never,
+        }
+    }
+}
+
+/// Bids the given percentile of the gas prices paid in recent blocks, or
+/// over the competitors when they pay more, bumping up to break even.
+#[derive(Debug, Clone, Copy)]
+pub struct Percentile {
+    pub percentile: u8,
+}
+
+impl GasStrategy for Percentile {
+    fn bid(&self, opportunity: &Opportunity, conditions: &Conditions) -> Bid {
+        let recent = &conditions.recent_gas_prices;
+        let percentile = match recent.len() {
+            0 => conditions.gas_price,
+            len => recent[(len - 1) * usize::from(self.percentile.min(100)) / 100],
+        };
+
+        let competing = conditions.competitors.iter().max().map(|p| p + gwei());
+
+        Bid {
+            initial: percentile.max(competing.unwrap_or_default()),
+            max: opportunity
+                .break_even()
+                .min(conditions.affordable(opportunity.max_gas_usage)),
+            bump: Bump::Outbid(gwei()),
+        }
+    }
+}
+
+/// Gas prices paid by the transactions of the last few blocks.
+#[derive(Debug, Clone, Default)]
+pub struct RecentGasPrices {
+    blocks: VecDeque<Vec<U256>>,
+    depth: usize,
+}
+
+impl RecentGasPrices {
+    pub fn new(depth: usize) -> RecentGasPrices {
+        RecentGasPrices {
+            blocks: VecDeque::new(),
+            depth,
+        }
+    }
+
+    pub fn push(&mut self, gas_prices: Vec<U256>) {
+        self.blocks.push_back(gas_prices);
+        while self.blocks.len() > self.depth {
+            self.blocks.pop_front();
+        }
+    }
+
+    pub fn sorted(&self) -> Vec<U256> {
+        let mut gas_prices: Vec<_> = self.blocks.iter().flatten().copied().collect();
+        gas_prices.sort();
+        gas_prices
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bids_by_strategy() {
+        let gwei = |n: u64| U256::from(n) * gwei();
+        let opportunity = Opportunity {
+            weth_profit: gwei(100) * 100_000,
+            expected_gas_usage: 100_000.into(),
+            max_gas_usage: 200_000.into(),
+        };
+
+        let mut recent = RecentGasPrices::new(2);
+        recent.push(vec![gwei(50)]);
+        recent.push(vec![gwei(10), gwei(30)]);
+        recent.push(vec![gwei(20), gwei(40)]);
+        let recent = recent.sorted();
+        assert_eq!(recent, vec![gwei(10), gwei(20), gwei(30), gwei(40)]);
+
+        let conditions = Conditions {
+            gas_price: gwei(20),
+            balance: gwei(80) * 200_000,
+            competitors: &[gwei(35)],
+            recent_gas_prices: &recent,
+        };
+
+        let scaled = ScaledGasPrice {
+            min_scale: 2,
+            max_scale: 5,
+        }
+        .bid(&opportunity, &conditions);
+        assert_eq!((scaled.initial, scaled.max), (gwei(40), gwei(80)));
+        assert_eq!(
+            scaled.bump.next(gwei(40), gwei(50), scaled.max),
+            Some(gwei(51))
+        );
+        assert_eq!(scaled.bump.next(gwei(40), gwei(80), scaled.max), None);
+
+        let share = ProfitShare { percent: 50 }.bid(&opportunity, &conditions);
+        assert_eq!((share.initial, share.max), (gwei(50), gwei(50)));
+        assert_eq!(share.bump.next(gwei(50), gwei(10), share.max), None);
+
+        let percentile = Percentile { percentile: 50 }.bid(&opportunity, &conditions);
+        assert_eq!((percentile.initial, percentile.max), (gwei(36), gwei(80)));
+        assert!(percentile.viable());
+
+        let lowest = Percentile { percentile: 0 }.bid(
+            &opportunity,
+            &Conditions {
+                competitors: &[],
+                ..conditions
+            },
+        );
+        assert_eq!(lowest.initial, gwei(10));
+    }
+}
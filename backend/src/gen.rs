@@ -1,7 +1,13 @@
+#![allow(clippy::all)]
+
 mod arbrito;
 mod balancerpool;
+mod erc20;
 mod uniswappair;
+mod uniswaprouter;
 
 pub use arbrito::Arbrito;
-pub use balancerpool::BalancerPool;
-pub use uniswappair::UniswapPair;
+pub use balancerpool::{balancer_pool, BalancerPool};
+pub use erc20::ERC20;
+pub use uniswappair::{uniswap_pair, UniswapPair};
+pub use uniswaprouter::UniswapRouter;
@@ -0,0 +1,293 @@
+use rusqlite::{params, types::Type, Connection, Row};
+use serde::Serialize;
+use std::{collections::HashMap, error::Error, str::FromStr};
+use web3::types::{H160, U256};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS attempts (
+        timestamp INTEGER NOT NULL,
+        block_number INTEGER NOT NULL,
+        uniswap_pair TEXT NOT NULL,
+        balancer_pool TEXT NOT NULL,
+        borrow_token TEXT NOT NULL,
+        profit_token TEXT NOT NULL,
+        result TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        weth_profit TEXT NOT NULL,
+        min_gas_price TEXT,
+        max_gas_price TEXT,
+        executed INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS attempts_timestamp ON attempts (timestamp);
+";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptResult {
+    NotProfit,
+    GrossProfit,
+    NetProfit,
+}
+
+impl AttemptResult {
+    fn as_str(self) -> &'static str {
+        match self {
+            AttemptResult::NotProfit => "not_profit",
+            AttemptResult::GrossProfit => "gross_profit",
+            AttemptResult::NetProfit => "net_profit",
+        }
+    }
+
+    fn from_str(result: &str) -> Option<AttemptResult> {
+        match result {
+            "not_profit" => Some(AttemptResult::NotProfit),
+            "gross_profit" => Some(AttemptResult::GrossProfit),
+            "net_profit" => Some(AttemptResult::NetProfit),
+            _ => None,
+        }
+    }
+}
+
+/// An attempt as journaled, borrowing the first token to get the second
+/// one back. Amounts are zero and gas bounds missing when the result has
+/// none.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Seconds since the epoch the attempt was made at.
+    pub timestamp: u64,
+    pub block_number: u64,
+    pub uniswap_pair: H160,
+    pub balancer_pool: H160,
+    pub borrow_token: H160,
+    pub profit_token: H160,
+    pub result: AttemptResult,
+    pub amount: U256,
+    pub weth_profit: U256,
+    pub min_gas_price: Option<U256>,
+    pub max_gas_price: Option<U256>,
+    /// Whether the attempt was handed to the executor.
+    pub executed: bool,
+}
+
+impl Entry {
+    fn from_row(row: &Row) -> rusqlite::Result<Entry> {
+        Ok(Entry {
+            timestamp: row.get::<_, i64>(0)? as u64,
+            block_number: row.get::<_, i64>(1)? as u64,
+            uniswap_pair: text(row, 2, address)?,
+            balancer_pool: text(row, 3, address)?,
+            borrow_token: text(row, 4, address)?,
+            profit_token: text(row, 5, address)?,
+            result: text(row, 6, AttemptResult::from_str)?,
+            amount: text(row, 7, amount)?,
+            weth_profit: text(row, 8, amount)?,
+            min_gas_price: optional_text(row, 9, amount)?,
+            max_gas_price: optional_text(row, 10, amount)?,
+            executed: row.get(11)?,
+        })
+    }
+}
+
+fn address(text: &str) -> Option<H160> {
+    H160::from_str(text.strip_prefix("0x")?).ok()
+}
+
+fn amount(text: &str) -> Option<U256> {
+    U256::from_dec_str(text).ok()
+}
+
+fn invalid(index: usize, text: &str) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        index,
+        Type::Text,
+        format!("invalid value {}", text).into(),
+    )
+}
+
+fn text<T>(row: &Row, index: usize, parse: impl Fn(&str) -> Option<T>) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    parse(&text).ok_or_else(|| invalid(index, &text))
+}
+
+fn optional_text<T>(
+    row: &Row,
+    index: usize,
+    parse: impl Fn(&str) -> Option<T>,
+) -> rusqlite::Result<Option<T>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(text) => parse(&text).map(Some).ok_or_else(|| invalid(index, &text)),
+        None => Ok(None),
+    }
+}
+
+/// How often a pair was found net profitable over some time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairSummary {
+    pub uniswap_pair: H160,
+    pub balancer_pool: H160,
+    pub attempts: u64,
+    pub executed: u64,
+    pub best_weth_profit: U256,
+    pub last_block_number: u64,
+}
+
+/// Every attempt made, kept in a SQLite database.
+pub struct Journal {
+    connection: Connection,
+}
+
+impl Journal {
+    pub fn open(path: &str) -> Result<Journal, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Journal { connection })
+    }
+
+    /// Journals the attempts of a block all at once.
+    pub fn record(&mut self, entries: &[Entry]) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO attempts VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+
+            for entry in entries {
+                insert.execute(params![
+                    entry.timestamp as i64,
+                    entry.block_number as i64,
+                    format!("{:?}", entry.uniswap_pair),
+                    format!("{:?}", entry.balancer_pool),
+                    format!("{:?}", entry.borrow_token),
+                    format!("{:?}", entry.profit_token),
+                    entry.result.as_str(),
+                    entry.amount.to_string(),
+                    entry.weth_profit.to_string(),
+                    entry.min_gas_price.map(|price| price.to_string()),
+                    entry.max_gas_price.map(|price| price.to_string()),
+                    entry.executed,
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The attempts with the given result made since the given time.
+    pub fn entries(&self, result: AttemptResult, since: u64) -> Result<Vec<Entry>, Box<dyn Error>> {
+        let mut select = self.connection.prepare(
+            "SELECT * FROM attempts WHERE result = ?1 AND timestamp >= ?2 ORDER BY block_number",
+        )?;
+
+        let entries = select
+            .query_map(params![result.as_str(), since as i64], Entry::from_row)?
+            .collect::<Result<_, _>>()?;
+        Ok(entries)
+    }
+
+    /// The pairs found net profitable since the given time, most often first.
+    pub fn net_profitable_pairs(&self, since: u64) -> Result<Vec<PairSummary>, Box<dyn Error>> {
+        let mut pairs: HashMap<(H160, H160), PairSummary> = HashMap::new();
+
+        for entry in self.entries(AttemptResult::NetProfit, since)? {
+            let pair = pairs
+                .entry((entry.uniswap_pair, entry.balancer_pool))
+                .or_insert_with(|| PairSummary {
+                    uniswap_pair: entry.uniswap_pair,
+                    balancer_pool: entry.balancer_pool,
+                    attempts: 0,
+                    executed: 0,
+                    best_weth_profit: U256::zero(),
+                    last_block_number: 0,
+                });
+
+            pair.attempts += 1;
+            pair.executed += entry.executed as u64;
+            pair.best_weth_profit = pair.best_weth_profit.max(entry.weth_profit);
+            pair.last_block_number = pair.last_block_number.max(entry.block_number);
+        }
+
+        let mut pairs: Vec<_> = pairs.into_values().collect();
+        pairs.sort_by_key(|pair| std::cmp::Reverse((pair.attempts, pair.best_weth_profit)));
+        Ok(pairs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn answers_which_pairs_were_net_profitable() {
+        let entry = |timestamp, pair, result, weth_profit: u64, executed| Entry {
+            timestamp,
+            block_number: timestamp,
+            uniswap_pair: H160::from_low_u64_be(pair),
+            balancer_pool: H160::from_low_u64_be(10),
+            borrow_token: H160::from_low_u64_be(1),
+            profit_token: H160::from_low_u64_be(2),
+            result,
+            amount: U256::exp10(20),
+            weth_profit: weth_profit.into(),
+            min_gas_price: Some(U256::exp10(10)).filter(|_| result == AttemptResult::NetProfit),
+            max_gas_price: Some(U256::exp10(11)).filter(|_| result == AttemptResult::NetProfit),
+            executed,
+        };
+
+        let path = std::env::temp_dir().join(format!("journal-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut journal = Journal::open(path).unwrap();
+
+        journal
+            .record(&[
+                entry(1, 3, AttemptResult::NetProfit, 500, true),
+                entry(1, 4, AttemptResult::NotProfit, 0, false),
+            ])
+            .unwrap();
+        journal
+            .record(&[
+                entry(2, 3, AttemptResult::NetProfit, 700, false),
+                entry(2, 4, AttemptResult::NetProfit, 900, true),
+                entry(2, 5, AttemptResult::GrossProfit, 100, false),
+            ])
+            .unwrap();
+
+        let first = entry(1, 3, AttemptResult::NetProfit, 500, true);
+        assert_eq!(
+            journal.entries(AttemptResult::NetProfit, 0).unwrap()[0],
+            first
+        );
+        assert_eq!(
+            journal
+                .entries(AttemptResult::GrossProfit, 2)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let pairs = journal.net_profitable_pairs(0).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            pairs,
+            vec![
+                PairSummary {
+                    uniswap_pair: H160::from_low_u64_be(3),
+                    balancer_pool: H160::from_low_u64_be(10),
+                    attempts: 2,
+                    executed: 1,
+                    best_weth_profit: 700.into(),
+                    last_block_number: 2,
+                },
+                PairSummary {
+                    uniswap_pair: H160::from_low_u64_be(4),
+                    balancer_pool: H160::from_low_u64_be(10),
+                    attempts: 1,
+                    executed: 1,
+                    best_weth_profit: 900.into(),
+                    last_block_number: 2,
+                },
+            ]
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::metrics;
 use ethcontract::{BlockId, BlockNumber, Web3};
-use futures::{Future, FutureExt, Stream, StreamExt};
+use futures::{future::join_all, Future, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -7,48 +9,100 @@ use std::{
 use tokio::sync::{mpsc, oneshot};
 use web3::{
     transports::Ipc,
-    types::{H160, U256, U64},
+    types::{FilterBuilder, Log, Transaction, H160, H256, U256, U64},
 };
 
-#[derive(Clone, Copy, Debug)]
-pub struct Block {
-    pub id: BlockId,
+/// Everything a new head is processed with, so that it can be recorded and
+/// replayed without a node.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Head {
     pub number: U64,
+    pub hash: H256,
+    /// Hash of the block the logs are taken after, which is the head itself
+    /// when there are none. Chains heads together even when some are skipped.
+    pub parent_hash: H256,
     pub gas_price: U256,
-    pub balance: U256,
-    pub nonce: U256,
+    /// Nonce and balance of every executor account at the block.
+    pub accounts: Vec<(H160, U256, U256)>,
+    /// Logs of the tracked markets since the previous head.
+    pub logs: Vec<Log>,
+    /// Transactions mined in the block.
+    pub transactions: Vec<Transaction>,
 }
 
-impl Block {
-    async fn fetch(web3: Web3<Ipc>, addr: H160, number: U64) -> Block {
+impl Head {
+    async fn fetch(
+        web3: &Web3<Ipc>,
+        accounts: &[H160],
+        markets: Vec<H160>,
+        from: U64,
+        number: U64,
+    ) -> Result<Head, web3::Error> {
         let block_number = BlockNumber::Number(number);
 
-        let eth = web3.eth();
-        let (nonce, balance, gas_price) = tokio::join!(
-            eth.transaction_count(addr, Some(block_number)),
-            eth.balance(addr, Some(block_number)),
+        let eth = &web3.eth();
+        let filter = FilterBuilder::default()
+            .from_block(BlockNumber::Number(from))
+            .to_block(block_number)
+            .address(markets)
+            .build();
+
+        let (states, gas_price, logs, block, parent) = tokio::join!(
+            join_all(accounts.iter().map(|&address| async move {
+                tokio::join!(
+                    eth.transaction_count(address, Some(block_number)),
+                    eth.balance(address, Some(block_number)),
+                )
+            })),
             eth.gas_price(),
+            async {
+                if from > number {
+                    return Ok(vec![]);
+                }
+                eth.logs(filter).await
+            },
+            eth.block_with_txs(BlockId::Number(block_number)),
+            eth.block(BlockId::Number(BlockNumber::Number(from - 1))),
         );
 
-        Block {
-            id: BlockId::Number(block_number),
-            gas_price: gas_price.expect("failed fetching block gas_price"),
-            balance: balance.expect("failed fetching block balance"),
-            nonce: nonce.expect("failed fetching block nonce"),
+        let accounts = accounts
+            .iter()
+            .zip(states)
+            .map(|(&address, (nonce, balance))| Ok((address, nonce?, balance?)))
+            .collect::<Result<_, web3::Error>>()?;
+
+        let (hash, transactions) = block?.map_or_else(Default::default, |b| {
+            (b.hash.unwrap_or_default(), b.transactions)
+        });
+
+        Ok(Head {
+            parent_hash: parent?.and_then(|b| b.hash).unwrap_or_default(),
+            gas_price: gas_price?,
+            logs: logs?,
+            transactions,
+            accounts,
             number,
-        }
+            hash,
+        })
     }
 }
 
 pub struct LatestBlock {
-    requests_tx: mpsc::UnboundedSender<oneshot::Sender<Block>>,
-    request_rx: Option<oneshot::Receiver<Block>>,
+    requests_tx: mpsc::UnboundedSender<oneshot::Sender<Head>>,
+    request_rx: Option<oneshot::Receiver<Head>>,
 }
 
 impl LatestBlock {
-    pub fn new(web3: Web3<Ipc>, executor_address: H160) -> LatestBlock {
+    /// Streams the latest heads of the blocks after `number`, with the state of
+    /// the given accounts and the logs of the given markets.
+    pub fn new(
+        web3: Web3<Ipc>,
+        accounts: Vec<H160>,
+        markets: Vec<H160>,
+        number: U64,
+    ) -> LatestBlock {
         let (requests_tx, requests_rx) = mpsc::unbounded_channel();
-        tokio::spawn(task(web3, executor_address, requests_rx));
+        tokio::spawn(task(web3, accounts, markets, number, requests_rx));
 
         LatestBlock {
             request_rx: None,
@@ -58,7 +112,7 @@ impl LatestBlock {
 }
 
 impl Stream for LatestBlock {
-    type Item = Block;
+    type Item = Head;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         match &mut self.request_rx {
@@ -83,8 +137,10 @@ impl Stream for LatestBlock {
 
 async fn task(
     web3: Web3<Ipc>,
-    executor_address: H160,
-    mut requests_rx: mpsc::UnboundedReceiver<oneshot::Sender<Block>>,
+    accounts: Vec<H160>,
+    markets: Vec<H160>,
+    mut number: U64,
+    mut requests_rx: mpsc::UnboundedReceiver<oneshot::Sender<Head>>,
 ) {
     let mut request = None;
     let mut open = true;
@@ -103,12 +159,30 @@ async fn task(
             },
             header = stream.next() => match header {
                 None => break,
-                Some(head) => {
+                Some(Err(err)) => {
+                    metrics::RPC_ERRORS.with_label_values(&["new_heads"]).inc();
+                    log::warn!("Failed reading new block head: {}", err);
+                }
+                Some(Ok(head)) => {
                     if let Some(tx) = request.take() {
-                        let head = head.expect("error reading new block head");
-                        let number = head.number.expect("block without a number?");
-                        let block = Block::fetch(web3.clone(), executor_address, number);
-                        tokio::spawn(block.map(move |b| tx.send(b).expect("failed response")));
+                        let head_number = head.number.expect("block without a number?");
+
+                        // Heads skipped while nothing was requested, or whose
+                        // fetch failed, have their logs taken by the next one.
+                        // Heads at the same or a lower height replace the
+                        // previous ones, as after a reorg.
+                        let from = number + 1;
+                        match Head::fetch(&web3, &accounts, markets.clone(), from, head_number).await {
+                            Ok(head) => {
+                                number = head_number;
+                                tx.send(head).expect("failed response");
+                            }
+                            Err(err) => {
+                                metrics::RPC_ERRORS.with_label_values(&["head"]).inc();
+                                log::warn!("Failed fetching block head {}: {}", head_number, err);
+                                request = Some(tx);
+                            }
+                        }
                     }
                 }
             }
@@ -0,0 +1,324 @@
+use crate::preflight::Revert;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+};
+use web3::types::{Log, H160, H256, U256, U64};
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Topic of the ERC20 `Transfer(address,address,uint256)` event.
+const TRANSFER_TOPIC: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+
+/// The transaction an execution was mined with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Mined {
+    pub hash: H256,
+    pub block_number: U64,
+    pub gas_used: U256,
+    pub gas_price: U256,
+    /// Which of the gas bumps got mined, the first send being 0.
+    pub replacement: usize,
+}
+
+impl Mined {
+    pub fn gas_cost(&self) -> U256 {
+        self.gas_used * self.gas_price
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Status {
+    /// Mined successfully, crediting the profit token balance by the amount,
+    /// when it could be told.
+    Succeeded { mined: Mined, profit: Option<U256> },
+    Reverted {
+        mined: Mined,
+        revert: Option<Revert>,
+    },
+    /// Sent but never mined.
+    Dropped,
+    /// Sent, but how it ended up couldn't be told.
+    Unknown,
+}
+
+/// How an execution ended up.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Outcome {
+    /// Seconds since the epoch the execution was settled at.
+    pub timestamp: u64,
+    /// Block the attempt was computed on.
+    pub block_number: U64,
+    pub uniswap_pair: H160,
+    pub balancer_pool: H160,
+    pub borrow_token: H160,
+    pub profit_token: H160,
+    pub expected_weth_profit: U256,
+    pub status: Status,
+}
+
+impl Outcome {
+    pub fn day(&self) -> u64 {
+        self.timestamp / SECONDS_PER_DAY
+    }
+
+    pub fn gas_cost(&self) -> U256 {
+        match &self.status {
+            Status::Succeeded { mined, .. } | Status::Reverted { mined, .. } => mined.gas_cost(),
+            Status::Dropped | Status::Unknown => U256::zero(),
+        }
+    }
+}
+
+/// Totals of the executions of a pair crediting a token over a day.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub executions: u64,
+    pub succeeded: u64,
+    pub reverted: u64,
+    pub dropped: u64,
+    pub unknown: u64,
+    /// Spent on gas, in wei.
+    pub gas_cost: U256,
+    /// Credited to the Arbrito balance, in the profit token.
+    pub profit: U256,
+}
+
+impl Entry {
+    fn add(&mut self, outcome: &Outcome) {
+        self.executions += 1;
+        self.gas_cost += outcome.gas_cost();
+
+        match &outcome.status {
+            Status::Succeeded { profit, .. } => {
+                self.succeeded += 1;
+                self.profit += profit.unwrap_or_default();
+            }
+            Status::Reverted { .. } => self.reverted += 1,
+            Status::Dropped => self.dropped += 1,
+            Status::Unknown => self.unknown += 1,
+        }
+    }
+}
+
+/// Day, Uniswap pair, Balancer pool and profit token of a ledger entry.
+pub type Key = (u64, H160, H160, H160);
+
+/// Realized profits and losses by day, pair and token.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    entries: BTreeMap<Key, Entry>,
+}
+
+impl Ledger {
+    pub fn new(outcomes: &[Outcome]) -> Ledger {
+        let mut ledger = Ledger::default();
+        for outcome in outcomes {
+            ledger.record(outcome);
+        }
+
+        ledger
+    }
+
+    pub fn record(&mut self, outcome: &Outcome) {
+        let key = (
+            outcome.day(),
+            outcome.uniswap_pair,
+            outcome.balancer_pool,
+            outcome.profit_token,
+        );
+
+        self.entries.entry(key).or_default().add(outcome);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Key, &Entry)> {
+        self.entries.iter()
+    }
+
+    /// Entries of the day summed up by profit token.
+    pub fn day(&self, day: u64) -> BTreeMap<H160, Entry> {
+        let mut tokens: BTreeMap<H160, Entry> = BTreeMap::new();
+        let start = (day, H160::zero(), H160::zero(), H160::zero());
+        let end = (day + 1, H160::zero(), H160::zero(), H160::zero());
+        for ((_, _, _, token), entry) in self.entries.range(start..end) {
+            let total = tokens.entry(*token).or_default();
+            total.executions += entry.executions;
+            total.succeeded += entry.succeeded;
+            total.reverted += entry.reverted;
+            total.dropped += entry.dropped;
+            total.unknown += entry.unknown;
+            total.gas_cost += entry.gas_cost;
+            total.profit += entry.profit;
+        }
+
+        tokens
+    }
+}
+
+/// Appends the outcome to a file of json outcomes, one per line.
+pub fn append(path: &str, outcome: &Outcome) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut line = serde_json::to_string(outcome)?;
+    line.push('\n');
+
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+pub fn read(path: &str) -> Result<Vec<Outcome>, Box<dyn Error>> {
+    let mut outcomes = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        outcomes.push(serde_json::from_str(&line?)?);
+    }
+
+    Ok(outcomes)
+}
+
+/// The profit an execution credited Arbrito with, from the transfer logs of
+/// its receipt: what the Balancer pool sent of the profit token minus what was
+/// paid back to the Uniswap pair with it. None without the former. Logs that
+/// aren't plain transfers of an amount are skipped.
+pub fn credited(
+    logs: &[Log],
+    token: H160,
+    arbrito: H160,
+    balancer_pool: H160,
+    uniswap_pair: H160,
+) -> Option<U256> {
+    let transferred = |from: H160, to: H160| {
+        logs.iter()
+            .filter(|log| {
+                log.address == token
+                    && log.topics.len() == 3
+                    && log.topics[0] == H256(TRANSFER_TOPIC)
+                    && log.topics[1] == H256::from(from)
+                    && log.topics[2] == H256::from(to)
+                    && log.data.0.len() == 32
+            })
+            .map(|log| U256::from_big_endian(&log.data.0))
+            .fold(None, |sum: Option<U256>, amount| {
+                Some(sum.unwrap_or_default().saturating_add(amount))
+            })
+    };
+
+    let received = transferred(balancer_pool, arbrito)?;
+    let payback = transferred(arbrito, uniswap_pair).unwrap_or_default();
+    Some(received.saturating_sub(payback))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sums_up_outcomes_by_day_pair_and_token() {
+        let weth = H160::from_low_u64_be(1);
+        let outcome = |timestamp, pair, status| Outcome {
+            timestamp,
+            block_number: 1.into(),
+            uniswap_pair: H160::from_low_u64_be(pair),
+            balancer_pool: H160::from_low_u64_be(10),
+            borrow_token: H160::from_low_u64_be(2),
+            profit_token: weth,
+            expected_weth_profit: 100.into(),
+            status,
+        };
+        let mined = |replacement| Mined {
+            hash: H256::zero(),
+            block_number: 2.into(),
+            gas_used: 10.into(),
+            gas_price: 2.into(),
+            replacement,
+        };
+
+        let outcomes = vec![
+            outcome(
+                10,
+                3,
+                Status::Succeeded {
+                    mined: mined(0),
+                    profit: Some(80.into()),
+                },
+            ),
+            outcome(
+                20,
+                4,
+                Status::Reverted {
+                    mined: mined(1),
+                    revert: Some(Revert::PaybackFailed),
+                },
+            ),
+            outcome(SECONDS_PER_DAY, 3, Status::Dropped),
+            outcome(SECONDS_PER_DAY, 3, Status::Unknown),
+        ];
+
+        let path = std::env::temp_dir().join(format!("outcomes-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        for outcome in &outcomes {
+            append(path, outcome).unwrap();
+        }
+        let read = read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read, outcomes);
+
+        let ledger = Ledger::new(&read);
+        assert_eq!(ledger.entries().count(), 3);
+
+        let first_day = &ledger.day(0)[&weth];
+        assert_eq!(first_day.executions, 2);
+        assert_eq!(first_day.succeeded, 1);
+        assert_eq!(first_day.reverted, 1);
+        assert_eq!(first_day.gas_cost, 40.into());
+        assert_eq!(first_day.profit, 80.into());
+
+        let second_day = &ledger.day(1)[&weth];
+        assert_eq!(second_day.executions, 2);
+        assert_eq!(second_day.dropped, 1);
+        assert_eq!(second_day.unknown, 1);
+        assert_eq!(second_day.gas_cost, 0.into());
+    }
+
+    #[test]
+    fn credits_transfers_of_the_receipt() {
+        let address = H160::from_low_u64_be;
+        let (token, other, arbrito, pool, pair) =
+            (address(1), address(2), address(3), address(4), address(5));
+        let transfer = |token, from, to, amount: u64| Log {
+            address: token,
+            topics: vec![H256(TRANSFER_TOPIC), H256::from(from), H256::from(to)],
+            data: web3::types::Bytes(H256::from_low_u64_be(amount).0.to_vec()),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        };
+
+        let mut oversized = transfer(token, pool, arbrito, 1);
+        oversized.data.0.push(0);
+
+        let logs = vec![
+            oversized,
+            transfer(other, pair, arbrito, 500),
+            transfer(other, arbrito, pool, 500),
+            transfer(token, pool, arbrito, 120),
+            transfer(token, arbrito, pair, 100),
+            transfer(token, pool, other, 1000),
+        ];
+        assert_eq!(credited(&logs, token, arbrito, pool, pair), Some(20.into()));
+        assert_eq!(credited(&logs[..3], token, arbrito, pool, pair), None);
+        assert_eq!(
+            TRANSFER_TOPIC,
+            web3::signing::keccak256(b"Transfer(address,address,uint256)")
+        );
+    }
+}
@@ -1,8 +1,33 @@
+pub mod accounts;
+pub mod admin;
 mod calc;
+mod calldata;
+pub mod competitors;
+pub mod dashboard;
+pub mod gas;
 pub mod gen;
+pub mod journal;
 pub mod latest_block;
+pub mod ledger;
+pub mod mempool;
+pub mod metadata;
+pub mod metrics;
+pub mod mock_transport;
+pub mod network;
 mod pairs;
 pub mod pending_tx;
+pub mod preflight;
+pub mod recording;
+pub mod relay;
+pub mod routers;
+mod selection;
+pub mod settings;
+pub mod signer;
+pub mod state;
 
-pub use calc::{max_profit, uniswap_out_given_in};
+pub use calc::{
+    balancer_in_given_out, balancer_out_given_in, max_profit, uniswap_in_given_out,
+    uniswap_out_given_in,
+};
 pub use pairs::{Pair, Pairs, Token};
+pub use selection::{select_disjoint, Candidate};
@@ -0,0 +1,181 @@
+use crate::pending_tx::PendingTx;
+use std::collections::HashMap;
+use web3::types::{H160, U256, U64};
+
+/// What happened to the mempool when a pending transaction was inserted.
+#[derive(Debug)]
+pub enum Insertion {
+    New,
+    /// Same sender and nonce as a cheaper one, which it took the place of.
+    Replaced(Box<PendingTx>),
+    /// Same sender and nonce as a pricier one, which is kept instead.
+    Underpriced,
+    /// Already in the mempool, as when seen again.
+    Duplicate,
+}
+
+/// Pending transactions by sender and nonce, as only one of them can be mined.
+pub struct Mempool {
+    txs: HashMap<(H160, U256), (U64, PendingTx)>,
+    ttl: u64,
+}
+
+impl Mempool {
+    /// Transactions are evicted after `ttl` blocks without being mined.
+    pub fn new(ttl: u64) -> Mempool {
+        Mempool {
+            txs: HashMap::new(),
+            ttl,
+        }
+    }
+
+    pub fn insert(&mut self, tx: PendingTx, seen: U64) -> Insertion {
+        let key = (tx.from, tx.nonce);
+
+        match self.txs.get(&key) {
+            Some((_, previous)) if previous.hash == tx.hash => Insertion::Duplicate,
+            Some((_, previous)) if previous.gas_price >= tx.gas_price => Insertion::Underpriced,
+            Some(_) => {
+                let (_, previous) = self.txs.insert(key, (seen, tx)).unwrap();
+                Insertion::Replaced(Box::new(previous))
+            }
+            None => {
+                self.txs.insert(key, (seen, tx));
+                Insertion::New
+            }
+        }
+    }
+
+    /// Drops every transaction whose nonce was used by the block's own
+    /// transactions or that went unmined for too long.
+    pub fn prune(&mut self, number: U64, mined: impl IntoIterator<Item = (H160, U256)>) {
+        let mut nonces: HashMap<H160, U256> = HashMap::new();
+        for (from, nonce) in mined {
+            let highest = nonces.entry(from).or_insert(nonce);
+            *highest = (*highest).max(nonce);
+        }
+
+        let ttl = self.ttl;
+        self.txs.retain(|(from, nonce), (seen, _)| {
+            nonces.get(from).is_none_or(|mined| nonce > mined) && *seen + ttl > number
+        });
+    }
+
+    pub fn txs(&self) -> impl Iterator<Item = &PendingTx> {
+        self.txs.values().map(|(_, tx)| tx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    /// Highest gas price among the transactions conflicting with an
+    /// arbitrage between the given pair and pool.
+    pub fn highest_gas_price(
+        &self,
+        token_from: H160,
+        token_to: H160,
+        uniswap_pair: H160,
+        balancer_pool: H160,
+    ) -> Option<U256> {
+        self.txs()
+            .filter(|tx| tx.conflicts(token_from, token_to, uniswap_pair, balancer_pool))
+            .map(|tx| tx.gas_price)
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        competitors::{Competitor, Competitors},
+        routers::Routers,
+    };
+    use std::collections::HashSet;
+    use web3::types::{Transaction, H256};
+
+    fn pending_tx(from: u64, nonce: u64, gas_price: u64) -> PendingTx {
+        let competitors = Competitors {
+            competitors: vec![Competitor {
+                address: H160::from_low_u64_be(1),
+                name: "bot".to_owned(),
+                selectors: vec![],
+            }],
+        };
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(from << 32 | nonce << 16 | gas_price),
+            from: H160::from_low_u64_be(from),
+            to: Some(H160::from_low_u64_be(1)),
+            nonce: nonce.into(),
+            input: vec![0; 4].into(),
+            gas_price: gas_price.into(),
+            ..Transaction::default()
+        };
+
+        PendingTx::from_transaction(
+            &tx,
+            &Routers {
+                addresses: HashMap::new(),
+                aggregators: HashSet::new(),
+                wrapped_native: H160::zero(),
+            },
+            &competitors,
+            &HashSet::new(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tracks_replacements_and_evictions() {
+        let any = H160::zero();
+        let mut mempool = Mempool::new(2);
+
+        assert!(matches!(
+            mempool.insert(pending_tx(2, 0, 10), 1.into()),
+            Insertion::New
+        ));
+        assert!(matches!(
+            mempool.insert(pending_tx(2, 1, 10), 1.into()),
+            Insertion::New
+        ));
+        assert!(matches!(
+            mempool.insert(pending_tx(3, 0, 20), 1.into()),
+            Insertion::New
+        ));
+        assert!(matches!(
+            mempool.insert(pending_tx(2, 0, 5), 1.into()),
+            Insertion::Underpriced
+        ));
+        assert!(matches!(
+            mempool.insert(pending_tx(2, 0, 10), 2.into()),
+            Insertion::Duplicate
+        ));
+        match mempool.insert(pending_tx(2, 0, 30), 2.into()) {
+            Insertion::Replaced(previous) => assert_eq!(previous.gas_price, 10.into()),
+            insertion => panic!("unexpected {:?}", insertion),
+        }
+        assert_eq!(mempool.len(), 3);
+        assert_eq!(
+            mempool.highest_gas_price(any, any, any, any),
+            Some(30.into())
+        );
+
+        mempool.prune(2.into(), vec![(H160::from_low_u64_be(2), 0.into())]);
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(
+            mempool.highest_gas_price(any, any, any, any),
+            Some(20.into())
+        );
+
+        mempool.prune(3.into(), vec![]);
+        assert!(mempool.is_empty());
+        assert_eq!(mempool.highest_gas_price(any, any, any, any), None);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::gen::{BalancerPool, UniswapPair, ERC20};
+use ethcontract::{errors::MethodError, BlockId};
+use futures::FutureExt;
+use std::collections::{HashMap, HashSet};
+use web3::{
+    futures::future::{join_all, try_join_all},
+    transports::Ipc,
+    types::{H160, U256},
+    Web3,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct UniswapPairMetadata {
+    pub token0: H160,
+    pub token1: H160,
+}
+
+#[derive(Clone, Debug)]
+pub struct BalancerPoolMetadata {
+    pub swap_fee: U256,
+    pub finalized: bool,
+    pub weights: HashMap<H160, U256>,
+}
+
+impl UniswapPairMetadata {
+    pub async fn fetch(contract: &UniswapPair) -> UniswapPairMetadata {
+        let (token0, token1) = tokio::join!(contract.token_0().call(), contract.token_1().call());
+
+        UniswapPairMetadata {
+            token0: token0.expect("unable to fetch token0"),
+            token1: token1.expect("unable to fetch token1"),
+        }
+    }
+}
+
+impl BalancerPoolMetadata {
+    pub async fn fetch(
+        contract: &BalancerPool,
+        tokens: &HashSet<H160>,
+        block: BlockId,
+    ) -> Result<BalancerPoolMetadata, MethodError> {
+        let (swap_fee, finalized) = tokio::join!(
+            contract.get_swap_fee().block(block).call(),
+            contract.is_finalized().block(block).call(),
+        );
+
+        let futs = tokens.iter().copied().map(|t| {
+            let req = contract.get_denormalized_weight(t).block(block);
+            req.call().map(move |r| r.map(|weight| (t, weight)))
+        });
+
+        Ok(BalancerPoolMetadata {
+            swap_fee: swap_fee?,
+            finalized: finalized?,
+            weights: try_join_all(futs).await?.into_iter().collect(),
+        })
+    }
+
+    /// Whether the pool can still be arbitraged between the two tokens,
+    /// which requires it to be finalized and equally weighted on both.
+    pub fn tradeable(&self, token0: H160, token1: H160) -> bool {
+        self.finalized && self.weights.get(&token0) == self.weights.get(&token1)
+    }
+}
+
+/// Decimals of the tokens implementing `decimals()`, leaving out the others,
+/// such as MKR, which declare it as a `bytes32` or not at all.
+pub async fn fetch_decimals(web3: &Web3<Ipc>, tokens: &[H160]) -> HashMap<H160, usize> {
+    let futs = tokens.iter().copied().map(|t| {
+        let req = ERC20::at(web3, t).decimals();
+        req.call().map(move |r| match r {
+            Ok(decimals) => Some((t, usize::from(decimals))),
+            Err(err) => {
+                log::warn!("Unable to fetch decimals of {:?}: {}", t, err);
+                None
+            }
+        })
+    });
+
+    join_all(futs).await.into_iter().flatten().collect()
+}
@@ -0,0 +1,124 @@
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge_vec, register_histogram, register_int_counter_vec, register_int_gauge, Encoder,
+    GaugeVec, Histogram, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::{convert::Infallible, net::SocketAddr};
+
+lazy_static! {
+    pub static ref BLOCK_PROCESSING_SECONDS: Histogram = register_histogram!(
+        "watch_pairs_block_processing_seconds",
+        "Time taken processing a new block"
+    )
+    .expect("failed registering metric");
+    pub static ref ATTEMPTS: IntCounterVec = register_int_counter_vec!(
+        "watch_pairs_attempts_total",
+        "Attempts computed, by result",
+        &["result"]
+    )
+    .expect("failed registering metric");
+    pub static ref EXECUTIONS: IntCounterVec = register_int_counter_vec!(
+        "watch_pairs_executions_total",
+        "Executions sent, then succeeded, failed, dropped or unknown",
+        &["outcome"]
+    )
+    .expect("failed registering metric");
+    pub static ref CONFLICTS: IntCounterVec = register_int_counter_vec!(
+        "watch_pairs_conflicts_total",
+        "Pending transactions conflicting with executions, by competitor",
+        &["competitor"]
+    )
+    .expect("failed registering metric");
+    pub static ref EXECUTOR_BALANCE: GaugeVec = register_gauge_vec!(
+        "watch_pairs_executor_balance_ether",
+        "Balance of the executor accounts",
+        &["address"]
+    )
+    .expect("failed registering metric");
+    pub static ref RPC_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "watch_pairs_rpc_errors_total",
+        "Failed node and relay requests after starting up, by call",
+        &["call"]
+    )
+    .expect("failed registering metric");
+    pub static ref MEMPOOL_TXS: IntCounterVec = register_int_counter_vec!(
+        "watch_pairs_mempool_txs_total",
+        "Relevant pending transactions seen, by how they went into the mempool",
+        &["insertion"]
+    )
+    .expect("failed registering metric");
+    pub static ref MEMPOOL_SIZE: IntGauge = register_int_gauge!(
+        "watch_pairs_mempool_size",
+        "Relevant pending transactions kept in the mempool"
+    )
+    .expect("failed registering metric");
+}
+
+/// Every registered metric in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("failed encoding metrics");
+    String::from_utf8(buffer).expect("metrics aren't utf8")
+}
+
+async fn respond(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match request.uri().path() {
+        "/metrics" => Response::builder()
+            .header(CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+
+    Ok(response.expect("failed building response"))
+}
+
+/// Serves the metrics at `/metrics` in the background, returning the address
+/// bound to.
+pub fn serve(address: &SocketAddr) -> Result<SocketAddr, hyper::Error> {
+    let make_service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(respond)) });
+    let server = Server::try_bind(address)?.serve(make_service);
+    let bound = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            log::error!("Metrics server failed: {}", err);
+        }
+    });
+
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_metrics() {
+        ATTEMPTS.with_label_values(&["net_profit"]).inc();
+        MEMPOOL_SIZE.set(3);
+
+        let address = serve(&([127, 0, 0, 1], 0).into()).unwrap();
+        let url = format!("http://{}", address);
+
+        let metrics = reqwest::get(&format!("{}/metrics", url))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(metrics.contains("watch_pairs_attempts_total{result=\"net_profit\"} 1"));
+        assert!(metrics.contains("watch_pairs_mempool_size 3"));
+
+        let missing = reqwest::get(&url).await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,133 @@
+use futures::future::{ready, Ready};
+use jsonrpc_core::{Call, Value};
+use std::sync::{Arc, Mutex};
+use web3::{
+    error::{Error, Result},
+    helpers,
+    types::{Bytes, RawTransaction, TransactionReceipt, H256, U256, U64},
+    RequestId, Transport,
+};
+
+const INTRINSIC_GAS: u64 = 21_000;
+
+#[derive(Debug, Default)]
+struct Chain {
+    head: U64,
+    /// Sent transactions by the block they were sent at.
+    sent: Vec<(H256, U64)>,
+}
+
+/// Stands in for the node when executing replayed attempts. Calls, signing
+/// and sending always succeed, and sent transactions are mined successfully
+/// by the first block after the one they were sent at.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    chain: Arc<Mutex<Chain>>,
+}
+
+impl MockTransport {
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Advances the chain to the given block.
+    pub fn mine(&self, number: U64) {
+        self.chain.lock().expect("mock chain lock poisoned").head = number;
+    }
+
+    pub fn sent(&self) -> Vec<H256> {
+        let chain = self.chain.lock().expect("mock chain lock poisoned");
+        chain.sent.iter().map(|(hash, _)| *hash).collect()
+    }
+
+    fn respond(&self, method: &str, params: Vec<Value>) -> Result<Value> {
+        let mut chain = self.chain.lock().expect("mock chain lock poisoned");
+
+        let value = match method {
+            "eth_blockNumber" => helpers::serialize(&chain.head),
+            "eth_call" => helpers::serialize(&Bytes::default()),
+            "eth_estimateGas" => helpers::serialize(&U256::from(INTRINSIC_GAS)),
+            "personal_signTransaction" => helpers::serialize(&RawTransaction {
+                raw: Bytes(serde_json::to_vec(&params).map_err(|_| Error::Internal)?),
+                ..RawTransaction::default()
+            }),
+            "eth_sendRawTransaction" | "eth_sendTransaction" => {
+                let hash = H256::from_low_u64_be(chain.sent.len() as u64 + 1);
+                let head = chain.head;
+                chain.sent.push((hash, head));
+                helpers::serialize(&hash)
+            }
+            "eth_getTransactionReceipt" => {
+                let hash: H256 = params
+                    .into_iter()
+                    .next()
+                    .and_then(|hash| serde_json::from_value(hash).ok())
+                    .ok_or_else(|| Error::Decoder("missing transaction hash".to_owned()))?;
+
+                let receipt = chain
+                    .sent
+                    .iter()
+                    .find(|(sent, number)| *sent == hash && *number < chain.head)
+                    .map(|(_, number)| TransactionReceipt {
+                        transaction_hash: hash,
+                        block_number: Some(number + 1),
+                        status: Some(U64::one()),
+                        ..TransactionReceipt::default()
+                    });
+
+                helpers::serialize(&receipt)
+            }
+            method => {
+                return Err(Error::Transport(format!(
+                    "mock transport can't answer {}",
+                    method
+                )))
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl Transport for MockTransport {
+    type Out = Ready<Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        (0, helpers::build_request(0, method, params))
+    }
+
+    fn send(&self, _: RequestId, request: Call) -> Self::Out {
+        ready(match request {
+            Call::MethodCall(call) => {
+                self.respond(&call.method, call.params.parse().unwrap_or_default())
+            }
+            _ => Err(Error::Internal),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use web3::Web3;
+
+    #[test]
+    fn mines_sent_transactions_on_the_next_block() {
+        let mock = MockTransport::new();
+        let eth = Web3::new(mock.clone()).eth();
+        mock.mine(1.into());
+
+        let hash = web3::block_on(eth.send_raw_transaction(Bytes(vec![1]))).unwrap();
+        assert_eq!(mock.sent(), vec![hash]);
+        assert!(web3::block_on(eth.transaction_receipt(hash))
+            .unwrap()
+            .is_none());
+
+        mock.mine(2.into());
+        let receipt = web3::block_on(eth.transaction_receipt(hash))
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.block_number, Some(2.into()));
+        assert_eq!(receipt.status, Some(1.into()));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::routers::{Protocol, Routers};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error};
+use web3::{
+    types::{H160, U256},
+    Transport, Web3,
+};
+
+const FILE_PATH: &str = "networks.toml";
+
+/// Where everything the binaries talk to lives on a given chain.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Network {
+    pub name: String,
+    pub chain_id: u64,
+    pub wrapped_native: H160,
+    /// Not every network has Arbrito deployed, which only watching needs.
+    pub arbrito_address: Option<H160>,
+    pub uniswap_subgraph: Option<String>,
+    pub balancer_subgraph: Option<String>,
+    /// Tokens pairs are fetched for.
+    #[serde(default)]
+    pub allowed_tokens: Vec<H160>,
+    #[serde(default)]
+    pub routers: HashMap<H160, Protocol>,
+    /// Routers and aggregators whose calls aren't decoded, by name, known so
+    /// that they aren't taken for competitors.
+    #[serde(default)]
+    pub aggregators: HashMap<H160, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Networks {
+    pub networks: Vec<Network>,
+}
+
+impl Networks {
+    pub fn read() -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(FILE_PATH)?;
+        let networks = toml::from_slice(&bytes)?;
+        Ok(networks)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Network> {
+        self.networks.iter().find(|network| network.name == name)
+    }
+
+    /// Reads the profiles and picks the one with the given name.
+    pub fn select(name: &str) -> Result<Network, Box<dyn Error>> {
+        let networks = Networks::read()?;
+        let network = networks
+            .get(name)
+            .ok_or_else(|| format!("unknown network {}", name))?;
+        Ok(network.clone())
+    }
+}
+
+impl Network {
+    pub fn routers(&self) -> Routers {
+        Routers {
+            addresses: self.routers.clone(),
+            aggregators: self.aggregators.keys().copied().collect(),
+            wrapped_native: self.wrapped_native,
+        }
+    }
+
+    /// Makes sure the node is on the chain the profile is for.
+    pub async fn check_chain_id<T: Transport>(&self, web3: &Web3<T>) {
+        let chain_id = web3
+            .eth()
+            .chain_id()
+            .await
+            .expect("failed fetching chain id");
+
+        assert_eq!(
+            chain_id,
+            U256::from(self.chain_id),
+            "node is not on the {} network",
+            self.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn parses_profiles() {
+        let networks: Networks = toml::from_str(include_str!("../networks.toml")).unwrap();
+
+        let mainnet = networks.get("mainnet").unwrap();
+        assert_eq!(mainnet.chain_id, 1);
+        assert!(mainnet.arbrito_address.is_some());
+        assert!(mainnet.allowed_tokens.contains(&mainnet.wrapped_native));
+
+        let routers = mainnet.routers();
+        let uniswap = H160::from_str("7a250d5630B4cF539739dF2C5dAcb4c659F2488D").unwrap();
+        assert_eq!(routers.protocol(uniswap), Some(Protocol::Uniswap));
+        assert_eq!(routers.addresses.len(), 5);
+        let paraswap = H160::from_str("1bD435F3C054b6e901B7b108a0ab7617C808677b").unwrap();
+        assert!(routers.known(paraswap));
+        assert!(routers.known(uniswap));
+        assert_eq!(routers.protocol(paraswap), None);
+
+        let kovan = networks.get("kovan").unwrap();
+        assert_eq!(kovan.chain_id, 42);
+        assert_eq!(kovan.routers().protocol(uniswap), Some(Protocol::Uniswap));
+        assert!(networks.get("moon").is_none());
+    }
+}
@@ -1,23 +1,41 @@
-use crate::pairs::Token;
+use crate::{
+    calc::{
+        balancer_in_given_out, balancer_out_given_in, uniswap_in_given_out, uniswap_out_given_in,
+    },
+    calldata,
+    competitors::Competitors,
+    gen::{BalancerPool, UniswapPair},
+    pairs::Token,
+    routers::{Protocol, Routers},
+    state::{BalancerPoolResolved, UniswapPairResolved},
+};
+use ethcontract::common::abi;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
+    convert::TryFrom,
     fmt::Debug,
-    str::FromStr,
 };
 use web3::types::{Transaction, H160, H256, U256};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingTx {
     pub gas_price: U256,
     pub hash: H256,
-    kind: Kind,
+    pub from: H160,
+    pub nonce: U256,
+    /// Name of the registered competitor sending or being called by it.
+    pub competitor: Option<String>,
+    pub kind: Kind,
 }
 
 impl PendingTx {
     pub fn from_transaction(
         tx: &Transaction,
-        uniswap_router_address: H160,
+        routers: &Routers,
+        competitors: &Competitors,
+        uniswap_pairs: &HashSet<H160>,
         balancer_pools: &HashSet<H160>,
         tokens: &HashMap<H160, Token>,
     ) -> Option<PendingTx> {
@@ -25,58 +43,226 @@ impl PendingTx {
             return None;
         }
 
-        WeskerOperation::parse_kind(tx)
-            .or_else(|| BalancerSwap::parse_kind(tx, balancer_pools, tokens))
-            .or_else(|| UniswapSwap::parse_kind(tx, uniswap_router_address, tokens))
+        let protocol = tx.to.and_then(|to| routers.protocol(to));
+        let competitor = competitors.identify(tx);
+
+        BalancerSwap::parse_kind(tx, balancer_pools, tokens)
+            .or_else(|| BalancerLiquidity::parse_kind(tx, balancer_pools))
+            .or_else(|| UniswapPairSwap::parse_kind(tx, uniswap_pairs))
+            .or_else(|| match protocol? {
+                protocol @ Protocol::Uniswap | protocol @ Protocol::Sushiswap => {
+                    UniswapSwap::parse_kind(tx, protocol, tokens)
+                        .or_else(|| UniswapLiquidity::parse_kind(tx, protocol, routers, tokens))
+                }
+                protocol @ Protocol::OneInch | protocol @ Protocol::ZeroEx => {
+                    AggregatorSwap::parse_kind(tx, protocol, routers, tokens)
+                }
+                Protocol::BalancerExchangeProxy => {
+                    BalancerBatchSwap::parse_kind(tx, routers, tokens)
+                }
+            })
+            .or_else(|| competitor.map(|_| Kind::CompetitorOperation(CompetitorOperation)))
             .map(|kind| PendingTx {
                 gas_price: tx.gas_price,
                 hash: tx.hash,
+                from: tx.from,
+                nonce: tx.nonce,
+                competitor: competitor.map(|c| c.name.clone()),
                 kind,
             })
     }
 
-    pub fn conflicts(&self, token_from: H160, token_to: H160, balancer_pool: H160) -> bool {
+    pub fn conflicts(
+        &self,
+        token_from: H160,
+        token_to: H160,
+        uniswap_pair: H160,
+        balancer_pool: H160,
+    ) -> bool {
         match &self.kind {
             Kind::UniswapSwap(s) => s.conflicts(token_from, token_to),
+            Kind::UniswapPairSwap(s) => s.pair == uniswap_pair,
+            Kind::UniswapLiquidity(s) => s.conflicts(token_from, token_to),
+            Kind::BalancerLiquidity(s) => s.pool == balancer_pool,
             Kind::BalancerSwap(s) => s.conflicts(token_from, token_to, balancer_pool),
-            Kind::WeskerOperation(_) => true,
+            Kind::BalancerBatchSwap(s) => s.conflicts(token_from, token_to, balancer_pool),
+            Kind::AggregatorSwap(s) => s.conflicts(token_from, token_to),
+            Kind::CompetitorOperation(_) => true,
         }
     }
+
+    /// Applies the transaction effects on the given state, as if it was mined.
+    /// Hops through unknown pairs or pools are skipped and swaps that would
+    /// revert on their slippage limits are left out entirely.
+    pub fn apply(
+        &self,
+        pairs: &mut HashMap<H160, UniswapPairResolved>,
+        pools: &mut HashMap<H160, BalancerPoolResolved>,
+        pair_index: &HashMap<(H160, H160), H160>,
+    ) {
+        match &self.kind {
+            Kind::UniswapSwap(s) => s.apply(pairs, pair_index),
+            Kind::UniswapPairSwap(s) => s.apply(pairs),
+            Kind::UniswapLiquidity(s) => s.apply(pairs, pair_index),
+            Kind::BalancerSwap(s) => s.apply(pools),
+            Kind::BalancerLiquidity(s) => s.apply(pools),
+            Kind::BalancerBatchSwap(s) => s.apply(pools),
+            Kind::AggregatorSwap(_) | Kind::CompetitorOperation(_) => {}
+        }
+    }
+
+    /// Largest change, in basis points, the transaction makes to a reserve or
+    /// balance of the given state when mined.
+    pub fn impact(
+        &self,
+        pairs: &HashMap<H160, UniswapPairResolved>,
+        pools: &HashMap<H160, BalancerPoolResolved>,
+        pair_index: &HashMap<(H160, H160), H160>,
+    ) -> u64 {
+        let (mut mined_pairs, mut mined_pools) = (pairs.clone(), pools.clone());
+        self.apply(&mut mined_pairs, &mut mined_pools, pair_index);
+
+        let pair_changes = mined_pairs.iter().flat_map(|(address, mined)| {
+            let pair = &pairs[address];
+            vec![
+                (pair.reserve0, mined.reserve0),
+                (pair.reserve1, mined.reserve1),
+            ]
+        });
+        let pool_changes = mined_pools.iter().flat_map(|(address, mined)| {
+            let pool = &pools[address];
+            mined
+                .balances
+                .iter()
+                .map(move |(token, balance)| (pool.balances[token], *balance))
+        });
+
+        pair_changes
+            .chain(pool_changes)
+            .map(|(before, after)| change_bps(before, after))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn change_bps(before: U256, after: U256) -> u64 {
+    let change = if after > before {
+        after - before
+    } else {
+        before - after
+    };
+
+    if change.is_zero() {
+        0
+    } else if before.is_zero() {
+        u64::MAX
+    } else {
+        (change * U256::from(10_000) / before)
+            .min(U256::from(u64::MAX))
+            .as_u64()
+    }
 }
 
-#[derive(Debug)]
-enum Kind {
+/// What a pending transaction was decoded as.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Kind {
     UniswapSwap(UniswapSwap),
+    UniswapPairSwap(UniswapPairSwap),
+    UniswapLiquidity(UniswapLiquidity),
     BalancerSwap(BalancerSwap),
-    WeskerOperation(WeskerOperation),
+    BalancerLiquidity(BalancerLiquidity),
+    BalancerBatchSwap(BalancerBatchSwap),
+    AggregatorSwap(AggregatorSwap),
+    CompetitorOperation(CompetitorOperation),
 }
 
-#[derive(Debug)]
-enum UniswapSwapMethod {
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SwapAmounts {
+    ExactIn {
+        amount_in: U256,
+        min_amount_out: U256,
+    },
+    ExactOut {
+        amount_out: U256,
+        max_amount_in: U256,
+    },
+}
+
+fn symbols(tokens: &[Option<Token>]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.as_ref().map_or("?", |token| &token.symbol))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+const UNISWAP_SWAPS: &[&str] = &[
+    "swapExactTokensForTokens",
+    "swapExactTokensForETH",
+    "swapTokensForExactTokens",
+    "swapTokensForExactETH",
+    "swapExactETHForTokens",
+    "swapETHForExactTokens",
+    "swapExactTokensForTokensSupportingFeeOnTransferTokens",
+    "swapExactTokensForETHSupportingFeeOnTransferTokens",
+    "swapExactETHForTokensSupportingFeeOnTransferTokens",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UniswapSwapMethod {
     ExactTokensForTokens,
     ExactETHForTokens,
     ExactTokensForETH,
     TokensForExactTokens,
     TokensForExactETH,
     ETHForExactTokens,
+    ExactTokensForTokensSupportingFeeOnTransferTokens,
+    ExactETHForTokensSupportingFeeOnTransferTokens,
+    ExactTokensForETHSupportingFeeOnTransferTokens,
+    /// 0x exchange proxy's shortcut through Uniswap or Sushiswap pairs.
+    SellToUniswap,
+}
+
+impl UniswapSwapMethod {
+    fn from_name(name: &str) -> Option<UniswapSwapMethod> {
+        Some(match name {
+            "swapExactTokensForTokens" => UniswapSwapMethod::ExactTokensForTokens,
+            "swapExactETHForTokens" => UniswapSwapMethod::ExactETHForTokens,
+            "swapExactTokensForETH" => UniswapSwapMethod::ExactTokensForETH,
+            "swapTokensForExactTokens" => UniswapSwapMethod::TokensForExactTokens,
+            "swapTokensForExactETH" => UniswapSwapMethod::TokensForExactETH,
+            "swapETHForExactTokens" => UniswapSwapMethod::ETHForExactTokens,
+            "swapExactTokensForTokensSupportingFeeOnTransferTokens" => {
+                UniswapSwapMethod::ExactTokensForTokensSupportingFeeOnTransferTokens
+            }
+            "swapExactETHForTokensSupportingFeeOnTransferTokens" => {
+                UniswapSwapMethod::ExactETHForTokensSupportingFeeOnTransferTokens
+            }
+            "swapExactTokensForETHSupportingFeeOnTransferTokens" => {
+                UniswapSwapMethod::ExactTokensForETHSupportingFeeOnTransferTokens
+            }
+            _ => return None,
+        })
+    }
 }
 
-struct UniswapSwap {
-    method: UniswapSwapMethod,
-    tokens: Vec<Option<Token>>,
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UniswapSwap {
+    pub protocol: Protocol,
+    pub method: UniswapSwapMethod,
+    pub amounts: SwapAmounts,
+    pub path: Vec<H160>,
+    pub tokens: Vec<Option<Token>>,
 }
 
 impl Debug for UniswapSwap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{:?}({})",
+            "{:?}:{:?}({})",
+            self.protocol,
             self.method,
-            self.tokens
-                .iter()
-                .map(|token| token.as_ref().map_or("?", |token| &token.symbol))
-                .collect::<Vec<_>>()
-                .join(" -> ")
+            symbols(&self.tokens)
         )
     }
 }
@@ -84,38 +270,81 @@ impl Debug for UniswapSwap {
 impl UniswapSwap {
     fn parse_kind(
         tx: &Transaction,
-        uniswap_router_address: H160,
+        protocol: Protocol,
         tokens: &HashMap<H160, Token>,
     ) -> Option<Kind> {
-        tx.to.filter(|&to| to == uniswap_router_address)?;
+        let (function, args) = calldata::decode(protocol.abi(), UNISWAP_SWAPS, &tx.input.0)?;
+        let method = UniswapSwapMethod::from_name(&function.name)?;
 
-        if (tx.input.0.len() - 4) % 32 != 0 {
-            return None;
-        }
+        let (amounts, path) = match method {
+            UniswapSwapMethod::ExactTokensForTokens
+            | UniswapSwapMethod::ExactTokensForETH
+            | UniswapSwapMethod::ExactTokensForTokensSupportingFeeOnTransferTokens
+            | UniswapSwapMethod::ExactTokensForETHSupportingFeeOnTransferTokens => {
+                let (amount_in, min_amount_out, path, _to, _deadline): (
+                    U256,
+                    U256,
+                    Vec<H160>,
+                    H160,
+                    U256,
+                ) = calldata::args(args)?;
 
-        let (method, tokens_offset) = match &tx.input.0[0..4] {
-            [0x88, 0x03, 0xdb, 0xee] => (UniswapSwapMethod::TokensForExactTokens, 7),
-            [0x38, 0xed, 0x17, 0x39] => (UniswapSwapMethod::ExactTokensForTokens, 7),
-            [0x4a, 0x25, 0xd9, 0x4a] => (UniswapSwapMethod::TokensForExactETH, 7),
-            [0x18, 0xcb, 0xaf, 0xe5] => (UniswapSwapMethod::ExactTokensForETH, 7),
-            [0xfb, 0x3b, 0xdb, 0x41] => (UniswapSwapMethod::ETHForExactTokens, 6),
-            [0x7f, 0xf3, 0x6a, 0xb5] => (UniswapSwapMethod::ExactETHForTokens, 6),
-            _ => return None,
+                let amounts = SwapAmounts::ExactIn {
+                    amount_in,
+                    min_amount_out,
+                };
+                (amounts, path)
+            }
+            UniswapSwapMethod::TokensForExactTokens | UniswapSwapMethod::TokensForExactETH => {
+                let (amount_out, max_amount_in, path, _to, _deadline): (
+                    U256,
+                    U256,
+                    Vec<H160>,
+                    H160,
+                    U256,
+                ) = calldata::args(args)?;
+
+                let amounts = SwapAmounts::ExactOut {
+                    amount_out,
+                    max_amount_in,
+                };
+                (amounts, path)
+            }
+            UniswapSwapMethod::ExactETHForTokens
+            | UniswapSwapMethod::ExactETHForTokensSupportingFeeOnTransferTokens => {
+                let (min_amount_out, path, _to, _deadline): (U256, Vec<H160>, H160, U256) =
+                    calldata::args(args)?;
+
+                let amounts = SwapAmounts::ExactIn {
+                    amount_in: tx.value,
+                    min_amount_out,
+                };
+                (amounts, path)
+            }
+            UniswapSwapMethod::ETHForExactTokens => {
+                let (amount_out, path, _to, _deadline): (U256, Vec<H160>, H160, U256) =
+                    calldata::args(args)?;
+
+                let amounts = SwapAmounts::ExactOut {
+                    amount_out,
+                    max_amount_in: tx.value,
+                };
+                (amounts, path)
+            }
+            UniswapSwapMethod::SellToUniswap => return None,
         };
 
-        let mut addr: H160 = H160::zero();
-        let token_matches: Vec<_> = tx.input.0[4..]
-            .chunks_exact(32)
-            .skip(tokens_offset - 1)
-            .map(|chunk| {
-                if chunk[0..12].iter().any(|b| *b != 0) {
-                    return None;
-                }
+        UniswapSwap::new_kind(protocol, method, amounts, path, tokens)
+    }
 
-                addr.assign_from_slice(&chunk[12..32]);
-                tokens.get(&addr).cloned()
-            })
-            .collect();
+    fn new_kind(
+        protocol: Protocol,
+        method: UniswapSwapMethod,
+        amounts: SwapAmounts,
+        path: Vec<H160>,
+        tokens: &HashMap<H160, Token>,
+    ) -> Option<Kind> {
+        let token_matches: Vec<_> = path.iter().map(|addr| tokens.get(addr).cloned()).collect();
 
         if token_matches.iter().all(Option::is_none) {
             return None;
@@ -123,29 +352,376 @@ impl UniswapSwap {
 
         Some(Kind::UniswapSwap(UniswapSwap {
             tokens: token_matches,
+            protocol,
+            amounts,
             method,
+            path,
         }))
     }
 
+    /// Swaps through other forks trade on their own pairs, so only Uniswap's
+    /// can conflict.
     fn conflicts(&self, token_from: H160, token_to: H160) -> bool {
-        self.tokens.iter().tuple_windows().any(|swap| match swap {
-            (Some(from), Some(to)) => from.address == token_from && to.address == token_to,
-            _ => false,
-        })
+        self.protocol == Protocol::Uniswap
+            && self
+                .path
+                .iter()
+                .tuple_windows()
+                .any(|(&from, &to)| from == token_from && to == token_to)
+    }
+
+    /// Mirrors the router, which prices every hop against the reserves from
+    /// before the swap and only then performs them in order. Only Uniswap's
+    /// own pairs are tracked, so swaps through other forks are left out.
+    fn apply(
+        &self,
+        pairs: &mut HashMap<H160, UniswapPairResolved>,
+        pair_index: &HashMap<(H160, H160), H160>,
+    ) {
+        if self.protocol != Protocol::Uniswap {
+            return;
+        }
+
+        let hops: Vec<_> = self
+            .path
+            .iter()
+            .tuple_windows()
+            .map(|(&from, &to)| (from, pair_index.get(&(from, to)).copied()))
+            .collect();
+
+        let mut swaps = vec![];
+
+        match self.amounts {
+            SwapAmounts::ExactIn {
+                amount_in,
+                min_amount_out,
+            } => {
+                let mut amount = amount_in;
+                for &(token_in, address) in &hops {
+                    let (address, pair) = match address.and_then(|a| Some((a, pairs.get(&a)?))) {
+                        Some(a) => a,
+                        None => break,
+                    };
+
+                    let (ri, ro) = pair.reserves(token_in);
+                    if amount > U256::from(u128::MAX) {
+                        return;
+                    }
+
+                    let amount_out = uniswap_out_given_in(ri, ro, amount);
+                    swaps.push((address, token_in, amount, amount_out));
+                    amount = amount_out;
+                }
+
+                if swaps.len() == hops.len() && amount < min_amount_out {
+                    return;
+                }
+            }
+            SwapAmounts::ExactOut {
+                amount_out,
+                max_amount_in,
+            } => {
+                let mut amount = amount_out;
+                for &(token_in, address) in hops.iter().rev() {
+                    let (address, pair) = match address.and_then(|a| Some((a, pairs.get(&a)?))) {
+                        Some(a) => a,
+                        None => break,
+                    };
+
+                    let (ri, ro) = pair.reserves(token_in);
+                    if amount >= ro {
+                        return;
+                    }
+
+                    let amount_in = uniswap_in_given_out(ri, ro, amount);
+                    swaps.push((address, token_in, amount_in, amount));
+                    amount = amount_in;
+                }
+
+                if swaps.len() == hops.len() && amount > max_amount_in {
+                    return;
+                }
+            }
+        }
+
+        // The router reverts as a whole when any of the hops would.
+        let mut touched: HashMap<_, _> = swaps
+            .iter()
+            .filter_map(|&(address, ..)| Some((address, pairs.get(&address)?.clone())))
+            .collect();
+
+        let swapped = swaps
+            .into_iter()
+            .all(|(address, token_in, amount_in, amount_out)| {
+                touched
+                    .get_mut(&address)
+                    .and_then(|pair| pair.swap(token_in, amount_in, amount_out))
+                    .is_some()
+            });
+
+        if swapped {
+            pairs.extend(touched);
+        }
+    }
+}
+
+/// Swaps calling straight into one of our pairs, having paid it beforehand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UniswapPairSwap {
+    pub pair: H160,
+    pub amount0_out: U256,
+    pub amount1_out: U256,
+}
+
+impl UniswapPairSwap {
+    fn parse_kind(tx: &Transaction, uniswap_pairs: &HashSet<H160>) -> Option<Kind> {
+        let pair = tx.to.filter(|to| uniswap_pairs.contains(to))?;
+
+        let abi = &UniswapPair::artifact().abi;
+        let (_, args) = calldata::decode(abi, &["swap"], &tx.input.0)?;
+        let (amount0_out, amount1_out, _to, _data): (U256, U256, H160, Vec<u8>) =
+            calldata::args(args)?;
+
+        Some(Kind::UniswapPairSwap(UniswapPairSwap {
+            amount0_out,
+            amount1_out,
+            pair,
+        }))
     }
+
+    /// Assumes the pair was paid just enough for the amount out, as the
+    /// payment itself can't be seen from the call.
+    fn apply(&self, pairs: &mut HashMap<H160, UniswapPairResolved>) {
+        let pair = match pairs.get_mut(&self.pair) {
+            Some(pair) => pair,
+            None => return,
+        };
+
+        let (reserve_in, reserve_out, amount_out) =
+            match (self.amount0_out.is_zero(), self.amount1_out.is_zero()) {
+                (false, true) => (&mut pair.reserve1, &mut pair.reserve0, self.amount0_out),
+                (true, false) => (&mut pair.reserve0, &mut pair.reserve1, self.amount1_out),
+                _ => return,
+            };
+
+        if amount_out >= *reserve_out {
+            return;
+        }
+
+        let amount_in = uniswap_in_given_out(*reserve_in, *reserve_out, amount_out);
+        if let Some(reserve) = reserve_in.checked_add(amount_in) {
+            *reserve_in = reserve;
+            *reserve_out -= amount_out;
+        }
+    }
+}
+
+const UNISWAP_LIQUIDITY: &[&str] = &[
+    "addLiquidity",
+    "addLiquidityETH",
+    "removeLiquidity",
+    "removeLiquidityETH",
+    "removeLiquidityWithPermit",
+    "removeLiquidityETHWithPermit",
+    "removeLiquidityETHSupportingFeeOnTransferTokens",
+    "removeLiquidityETHWithPermitSupportingFeeOnTransferTokens",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum LiquidityChange {
+    Add {
+        desired_a: U256,
+        desired_b: U256,
+        min_a: U256,
+        min_b: U256,
+    },
+    Remove,
 }
 
-#[derive(Debug)]
-enum BalancerSwapMethod {
+/// Liquidity added or removed through a router, on the pair of its tokens.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UniswapLiquidity {
+    pub protocol: Protocol,
+    pub method: String,
+    pub change: LiquidityChange,
+    pub token_a: H160,
+    pub token_b: H160,
+    pub tokens: [Option<Token>; 2],
+}
+
+impl Debug for UniswapLiquidity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}:{}({})",
+            self.protocol,
+            self.method,
+            symbols(&self.tokens)
+        )
+    }
+}
+
+impl UniswapLiquidity {
+    fn parse_kind(
+        tx: &Transaction,
+        protocol: Protocol,
+        routers: &Routers,
+        tokens: &HashMap<H160, Token>,
+    ) -> Option<Kind> {
+        let (function, args) = calldata::decode(protocol.abi(), UNISWAP_LIQUIDITY, &tx.input.0)?;
+
+        let (token_a, token_b, change) = match function.name.as_str() {
+            "addLiquidity" => {
+                let (token_a, token_b, desired_a, desired_b, min_a, min_b, _to, _deadline): (
+                    H160,
+                    H160,
+                    U256,
+                    U256,
+                    U256,
+                    U256,
+                    H160,
+                    U256,
+                ) = calldata::args(args)?;
+
+                let change = LiquidityChange::Add {
+                    desired_a,
+                    desired_b,
+                    min_a,
+                    min_b,
+                };
+                (token_a, token_b, change)
+            }
+            "addLiquidityETH" => {
+                let (token, desired_a, min_a, min_b, _to, _deadline): (
+                    H160,
+                    U256,
+                    U256,
+                    U256,
+                    H160,
+                    U256,
+                ) = calldata::args(args)?;
+
+                let change = LiquidityChange::Add {
+                    desired_b: tx.value,
+                    desired_a,
+                    min_a,
+                    min_b,
+                };
+                (token, routers.wrapped_native, change)
+            }
+            "removeLiquidity" | "removeLiquidityWithPermit" => {
+                let mut addresses = args.into_iter().map(abi::Token::into_address);
+                let (token_a, token_b) = (addresses.next()??, addresses.next()??);
+                (token_a, token_b, LiquidityChange::Remove)
+            }
+            _ => {
+                let token = args.into_iter().next()?.into_address()?;
+                (token, routers.wrapped_native, LiquidityChange::Remove)
+            }
+        };
+
+        let tokens = [tokens.get(&token_a).cloned(), tokens.get(&token_b).cloned()];
+        if let [None, None] = tokens {
+            return None;
+        }
+
+        Some(Kind::UniswapLiquidity(UniswapLiquidity {
+            method: function.name.clone(),
+            protocol,
+            change,
+            token_a,
+            token_b,
+            tokens,
+        }))
+    }
+
+    fn conflicts(&self, token_from: H160, token_to: H160) -> bool {
+        self.protocol == Protocol::Uniswap
+            && ((self.token_a, self.token_b) == (token_from, token_to)
+                || (self.token_a, self.token_b) == (token_to, token_from))
+    }
+
+    /// Mirrors the router's optimal amounts for additions. Removals depend on
+    /// the pair's total supply, which isn't tracked, so they are left out.
+    fn apply(
+        &self,
+        pairs: &mut HashMap<H160, UniswapPairResolved>,
+        pair_index: &HashMap<(H160, H160), H160>,
+    ) {
+        if self.protocol != Protocol::Uniswap {
+            return;
+        }
+
+        let (desired_a, desired_b, min_a, min_b) = match self.change {
+            LiquidityChange::Add {
+                desired_a,
+                desired_b,
+                min_a,
+                min_b,
+            } => (desired_a, desired_b, min_a, min_b),
+            LiquidityChange::Remove => return,
+        };
+
+        let pair = match pair_index
+            .get(&(self.token_a, self.token_b))
+            .and_then(|address| pairs.get_mut(address))
+        {
+            Some(pair) => pair,
+            None => return,
+        };
+
+        let (reserve_a, reserve_b) = pair.reserves(self.token_a);
+        let (amount_a, amount_b) = if reserve_a.is_zero() && reserve_b.is_zero() {
+            (desired_a, desired_b)
+        } else if reserve_a.is_zero() || reserve_b.is_zero() {
+            // The router's quote reverts on a single empty reserve.
+            return;
+        } else {
+            let optimal_b = desired_a.full_mul(reserve_b) / reserve_a;
+            if optimal_b <= desired_b.into() {
+                (desired_a, U256::try_from(optimal_b).unwrap_or_default())
+            } else {
+                let optimal_a = desired_b.full_mul(reserve_a) / reserve_b;
+                (U256::try_from(optimal_a).unwrap_or_default(), desired_b)
+            }
+        };
+
+        if amount_a < min_a || amount_b < min_b || amount_a > desired_a {
+            return;
+        }
+
+        // The pair reverts on reserves that don't fit in 112 bits.
+        let max_reserve = U256::from(u128::MAX >> 16);
+        let fits = |reserve: U256, amount| {
+            reserve
+                .checked_add(amount)
+                .is_some_and(|reserve| reserve <= max_reserve)
+        };
+        if !fits(reserve_a, amount_a) || !fits(reserve_b, amount_b) {
+            return;
+        }
+
+        pair.deposit(self.token_a, amount_a);
+        pair.deposit(self.token_b, amount_b);
+    }
+}
+
+const BALANCER_SWAPS: &[&str] = &["swapExactAmountIn", "swapExactAmountOut"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BalancerSwapMethod {
     ExactAmountOut,
     ExactAmountIn,
 }
 
-struct BalancerSwap {
-    method: BalancerSwapMethod,
-    token_in: Option<Token>,
-    token_out: Option<Token>,
-    pool: H160,
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BalancerSwap {
+    pub method: BalancerSwapMethod,
+    pub amounts: SwapAmounts,
+    pub token_in: H160,
+    pub token_out: H160,
+    pub tokens: [Option<Token>; 2],
+    pub pool: H160,
 }
 
 impl BalancerSwap {
@@ -156,36 +732,65 @@ impl BalancerSwap {
     ) -> Option<Kind> {
         let pool = tx.to.filter(|to| balancer_pools.contains(to))?;
 
-        if (tx.input.0.len() - 4) % 32 != 0 {
-            return None;
-        }
+        let abi = &BalancerPool::artifact().abi;
+        let (function, args) = calldata::decode(abi, BALANCER_SWAPS, &tx.input.0)?;
+        let (token_in, amount_in, token_out, amount_out, _max_price): (
+            H160,
+            U256,
+            H160,
+            U256,
+            U256,
+        ) = calldata::args(args)?;
 
-        let method = match &tx.input.0[0..4] {
-            [0x82, 0x01, 0xaa, 0x3f] => BalancerSwapMethod::ExactAmountIn,
-            [0x7c, 0x5e, 0x9e, 0xa4] => BalancerSwapMethod::ExactAmountOut,
+        let method = match function.name.as_str() {
+            "swapExactAmountIn" => BalancerSwapMethod::ExactAmountIn,
+            "swapExactAmountOut" => BalancerSwapMethod::ExactAmountOut,
             _ => return None,
         };
 
-        if tx.input.0[4..16].iter().any(|b| *b != 0) || tx.input.0[68..80].iter().any(|b| *b != 0) {
+        let swap = BalancerSwap::new(
+            method, pool, token_in, amount_in, token_out, amount_out, tokens,
+        );
+        if let [None, None] = swap.tokens {
             return None;
         }
 
-        let token_in = H160::from_slice(&tx.input.0[16..36]);
-        let token_in = tokens.get(&token_in).cloned();
-
-        let token_out = H160::from_slice(&tx.input.0[80..100]);
-        let token_out = tokens.get(&token_out).cloned();
+        Some(Kind::BalancerSwap(swap))
+    }
 
-        if let (None, None) = (&token_in, &token_out) {
-            return None;
-        }
+    /// Builds the swap from the pool's own arguments, where the limit on the
+    /// other side of the trade follows the exact amount.
+    fn new(
+        method: BalancerSwapMethod,
+        pool: H160,
+        token_in: H160,
+        amount_in: U256,
+        token_out: H160,
+        amount_out: U256,
+        tokens: &HashMap<H160, Token>,
+    ) -> BalancerSwap {
+        let amounts = match method {
+            BalancerSwapMethod::ExactAmountIn => SwapAmounts::ExactIn {
+                amount_in,
+                min_amount_out: amount_out,
+            },
+            BalancerSwapMethod::ExactAmountOut => SwapAmounts::ExactOut {
+                max_amount_in: amount_in,
+                amount_out,
+            },
+        };
 
-        Some(Kind::BalancerSwap(BalancerSwap {
+        BalancerSwap {
+            tokens: [
+                tokens.get(&token_in).cloned(),
+                tokens.get(&token_out).cloned(),
+            ],
             token_out,
             token_in,
+            amounts,
             method,
             pool,
-        }))
+        }
     }
 
     fn conflicts(&self, token_in: H160, token_out: H160, pool: H160) -> bool {
@@ -193,52 +798,1244 @@ impl BalancerSwap {
             return false;
         }
 
-        let ti_address = self.token_in.as_ref().map(|t| t.address);
-        let in_is_in = ti_address.map_or(false, |addr| token_in == addr);
+        self.token_in == token_in || self.token_out == token_out
+    }
+
+    /// Amounts in and out of the swap on the pool, or `None` if it would
+    /// revert on the pool's max in and out ratios or on its own limits.
+    fn quote(&self, pool: &BalancerPoolResolved, amounts: SwapAmounts) -> Option<(U256, U256)> {
+        let bi = *pool.balances.get(&self.token_in)?;
+        let bo = *pool.balances.get(&self.token_out)?;
 
-        let to_address = self.token_out.as_ref().map(|t| t.address);
-        let out_is_out = to_address.map_or(false, |addr| token_out == addr);
+        match amounts {
+            SwapAmounts::ExactIn {
+                amount_in,
+                min_amount_out,
+            } => {
+                if amount_in > bi / 2 {
+                    return None;
+                }
+
+                let amount_out = balancer_out_given_in(bi, bo, pool.swap_fee, amount_in);
+                Some((amount_in, amount_out)).filter(|_| amount_out >= min_amount_out)
+            }
+            SwapAmounts::ExactOut {
+                amount_out,
+                max_amount_in,
+            } => {
+                if amount_out > bo / 3 {
+                    return None;
+                }
 
-        in_is_in || out_is_out
+                let amount_in = balancer_in_given_out(bi, bo, pool.swap_fee, amount_out);
+                Some((amount_in, amount_out)).filter(|_| amount_in <= max_amount_in)
+            }
+        }
+    }
+
+    fn apply(&self, pools: &mut HashMap<H160, BalancerPoolResolved>) {
+        if let Some(pool) = pools.get_mut(&self.pool) {
+            if let Some((amount_in, amount_out)) = self.quote(pool, self.amounts) {
+                pool.swap(self.token_in, amount_in, self.token_out, amount_out);
+            }
+        }
     }
 }
 
 impl Debug for BalancerSwap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{:?}({})",
-            self.method,
-            vec![&self.token_in, &self.token_out]
-                .iter()
-                .map(|token| token.as_ref().map_or("?", |token| &token.symbol))
-                .collect::<Vec<_>>()
-                .join(" -> ")
-        )
+        write!(f, "{:?}({})", self.method, symbols(&self.tokens))
     }
 }
 
-#[derive(Debug)]
-struct WeskerOperation;
+const BALANCER_LIQUIDITY: &[&str] = &[
+    "joinPool",
+    "exitPool",
+    "joinswapExternAmountIn",
+    "joinswapPoolAmountOut",
+    "exitswapPoolAmountIn",
+    "exitswapExternAmountOut",
+];
+
+/// Liquidity joining or exiting one of our pools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalancerLiquidity {
+    pub method: String,
+    pub pool: H160,
+    /// Single token amounts, known upfront when set in the call itself.
+    pub exact: Option<(H160, U256)>,
+}
+
+impl BalancerLiquidity {
+    fn parse_kind(tx: &Transaction, balancer_pools: &HashSet<H160>) -> Option<Kind> {
+        let pool = tx.to.filter(|to| balancer_pools.contains(to))?;
+
+        let abi = &BalancerPool::artifact().abi;
+        let (function, args) = calldata::decode(abi, BALANCER_LIQUIDITY, &tx.input.0)?;
+
+        let exact = match function.name.as_str() {
+            "joinswapExternAmountIn" | "exitswapExternAmountOut" => {
+                let (token, amount, _limit): (H160, U256, U256) = calldata::args(args)?;
+                Some((token, amount))
+            }
+            _ => None,
+        };
+
+        Some(Kind::BalancerLiquidity(BalancerLiquidity {
+            method: function.name.clone(),
+            exact,
+            pool,
+        }))
+    }
+
+    /// Only applies single token amounts known upfront, under the same ratio
+    /// limits as swaps. The rest depend on the pool's total supply, which
+    /// isn't tracked.
+    fn apply(&self, pools: &mut HashMap<H160, BalancerPoolResolved>) {
+        let (token, amount) = match self.exact {
+            Some(exact) => exact,
+            None => return,
+        };
+
+        let balance = match pools
+            .get_mut(&self.pool)
+            .and_then(|pool| pool.balances.get_mut(&token))
+        {
+            Some(balance) => balance,
+            None => return,
+        };
+
+        if self.method == "joinswapExternAmountIn" {
+            if amount <= *balance / 2 {
+                if let Some(sum) = balance.checked_add(amount) {
+                    *balance = sum;
+                }
+            }
+        } else if amount <= *balance / 3 {
+            *balance -= amount;
+        }
+    }
+}
+
+const BALANCER_BATCH_SWAPS: &[&str] = &[
+    "batchSwapExactIn",
+    "batchSwapExactOut",
+    "multihopBatchSwapExactIn",
+    "multihopBatchSwapExactOut",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BalancerBatchSwapMethod {
+    BatchSwapExactIn,
+    BatchSwapExactOut,
+    MultihopBatchSwapExactIn,
+    MultihopBatchSwapExactOut,
+}
+
+/// Swaps routed through Balancer's exchange proxy, as sequences of pool swaps.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BalancerBatchSwap {
+    pub method: BalancerBatchSwapMethod,
+    pub amounts: SwapAmounts,
+    pub tokens: [Option<Token>; 2],
+    pub sequences: Vec<Vec<BalancerSwap>>,
+}
+
+impl BalancerBatchSwap {
+    fn parse_kind(
+        tx: &Transaction,
+        routers: &Routers,
+        tokens: &HashMap<H160, Token>,
+    ) -> Option<Kind> {
+        let abi = Protocol::BalancerExchangeProxy.abi();
+        let (function, args) = calldata::decode(abi, BALANCER_BATCH_SWAPS, &tx.input.0)?;
+
+        let (method, swaps, token_in, token_out, amounts) = match function.name.as_str() {
+            "batchSwapExactIn" | "multihopBatchSwapExactIn" => {
+                let (swaps, token_in, token_out, amount_in, min_amount_out): (
+                    abi::Token,
+                    H160,
+                    H160,
+                    U256,
+                    U256,
+                ) = calldata::args(args)?;
+
+                let method = if function.name == "batchSwapExactIn" {
+                    BalancerBatchSwapMethod::BatchSwapExactIn
+                } else {
+                    BalancerBatchSwapMethod::MultihopBatchSwapExactIn
+                };
+                let amounts = SwapAmounts::ExactIn {
+                    amount_in,
+                    min_amount_out,
+                };
+                (method, swaps, token_in, token_out, amounts)
+            }
+            "batchSwapExactOut" | "multihopBatchSwapExactOut" => {
+                let (swaps, token_in, token_out, max_amount_in): (abi::Token, H160, H160, U256) =
+                    calldata::args(args)?;
+
+                let method = if function.name == "batchSwapExactOut" {
+                    BalancerBatchSwapMethod::BatchSwapExactOut
+                } else {
+                    BalancerBatchSwapMethod::MultihopBatchSwapExactOut
+                };
+                let amounts = SwapAmounts::ExactOut {
+                    amount_out: U256::zero(),
+                    max_amount_in,
+                };
+                (method, swaps, token_in, token_out, amounts)
+            }
+            _ => return None,
+        };
+
+        let sequences: Vec<Vec<abi::Token>> = match method {
+            BalancerBatchSwapMethod::BatchSwapExactIn
+            | BalancerBatchSwapMethod::BatchSwapExactOut => {
+                let swaps: Vec<abi::Token> = calldata::args(vec![swaps])?;
+                swaps.into_iter().map(|swap| vec![swap]).collect()
+            }
+            BalancerBatchSwapMethod::MultihopBatchSwapExactIn
+            | BalancerBatchSwapMethod::MultihopBatchSwapExactOut => calldata::args(vec![swaps])?,
+        };
+
+        let leg_method = match amounts {
+            SwapAmounts::ExactIn { .. } => BalancerSwapMethod::ExactAmountIn,
+            SwapAmounts::ExactOut { .. } => BalancerSwapMethod::ExactAmountOut,
+        };
+
+        let sequences = sequences
+            .into_iter()
+            .map(|sequence| {
+                sequence
+                    .into_iter()
+                    .map(|swap| {
+                        let (pool, token_in, token_out, swap_amount, limit_amount, _max_price): (
+                            H160,
+                            H160,
+                            H160,
+                            U256,
+                            U256,
+                            U256,
+                        ) = calldata::tuple(swap)?;
 
-impl WeskerOperation {
-    fn parse_kind(tx: &Transaction) -> Option<Kind> {
-        if tx.to != Some(H160::from_str("0000000000007f150bd6f54c40a34d7c3d5e9f56").unwrap()) {
+                        let (amount_in, amount_out) = match leg_method {
+                            BalancerSwapMethod::ExactAmountIn => (swap_amount, limit_amount),
+                            BalancerSwapMethod::ExactAmountOut => (limit_amount, swap_amount),
+                        };
+
+                        Some(BalancerSwap::new(
+                            leg_method.clone(),
+                            pool,
+                            token_in,
+                            amount_in,
+                            token_out,
+                            amount_out,
+                            tokens,
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let (token_in, token_out) = (routers.token(token_in), routers.token(token_out));
+        let tokens = [
+            tokens.get(&token_in).cloned(),
+            tokens.get(&token_out).cloned(),
+        ];
+        if let [None, None] = tokens {
             return None;
         }
 
-        match &tx.input.0[0..4] {
-            [0x03, 0x03, 0x19, 0x1c]
-            | [0x00, 0x03, 0x19, 0x1c]
-            | [0x03, 0x02, 0x19, 0x1c]
-            | [0x01, 0x02, 0x19, 0x1c]
-            | [0x01, 0x03, 0x19, 0x1c]
-            | [0x03, 0x02, 0xe8, 0x92]
-            | [0x00, 0x02, 0x19, 0x1c]
-            | [0x01, 0x02, 0xe8, 0x92]
-            | [0x00, 0x02, 0xe8, 0x92]
-            | [0x03, 0x03, 0xe8, 0x92] => Some(Kind::WeskerOperation(WeskerOperation)),
-            _ => None,
+        Some(Kind::BalancerBatchSwap(BalancerBatchSwap {
+            sequences,
+            amounts,
+            tokens,
+            method,
+        }))
+    }
+
+    fn conflicts(&self, token_in: H160, token_out: H160, pool: H160) -> bool {
+        self.sequences
+            .iter()
+            .flatten()
+            .any(|swap| swap.conflicts(token_in, token_out, pool))
+    }
+
+    /// Mirrors the exchange proxy, which feeds every swap's output into the
+    /// next one of a sequence when selling exact amounts and prices the
+    /// sequence backwards from its last swap when buying them. The batch
+    /// reverts as a whole if any of its swaps would.
+    fn apply(&self, pools: &mut HashMap<H160, BalancerPoolResolved>) {
+        let mut touched: HashMap<_, _> = self
+            .sequences
+            .iter()
+            .flatten()
+            .filter_map(|swap| Some((swap.pool, pools.get(&swap.pool)?.clone())))
+            .collect();
+
+        if self.simulate(&mut touched).is_some() {
+            pools.extend(touched);
+        }
+    }
+
+    fn simulate(&self, pools: &mut HashMap<H160, BalancerPoolResolved>) -> Option<()> {
+        let mut total = U256::zero();
+        let mut complete = true;
+
+        for sequence in &self.sequences {
+            match self.amounts {
+                SwapAmounts::ExactIn { .. } => {
+                    let mut previous_out = None;
+                    let mut swapped = 0;
+                    for swap in sequence {
+                        let pool = match pools.get_mut(&swap.pool) {
+                            Some(pool) => pool,
+                            None => break,
+                        };
+
+                        let amounts = match (swap.amounts, previous_out) {
+                            (SwapAmounts::ExactIn { min_amount_out, .. }, Some(amount_in)) => {
+                                SwapAmounts::ExactIn {
+                                    amount_in,
+                                    min_amount_out,
+                                }
+                            }
+                            (amounts, _) => amounts,
+                        };
+
+                        let (amount_in, amount_out) = swap.quote(pool, amounts)?;
+                        pool.swap(swap.token_in, amount_in, swap.token_out, amount_out)?;
+                        previous_out = Some(amount_out);
+                        swapped += 1;
+                    }
+
+                    if swapped == sequence.len() {
+                        total = total.saturating_add(previous_out.unwrap_or_default());
+                    } else {
+                        complete = false;
+                    }
+                }
+                SwapAmounts::ExactOut { .. } => {
+                    let mut quotes = vec![];
+                    let mut next_in = None;
+                    for swap in sequence.iter().rev() {
+                        let pool = match pools.get(&swap.pool) {
+                            Some(pool) => pool,
+                            None => break,
+                        };
+
+                        let amounts = match (swap.amounts, next_in) {
+                            (SwapAmounts::ExactOut { max_amount_in, .. }, Some(amount_out)) => {
+                                SwapAmounts::ExactOut {
+                                    amount_out,
+                                    max_amount_in,
+                                }
+                            }
+                            (amounts, _) => amounts,
+                        };
+
+                        let (amount_in, amount_out) = swap.quote(pool, amounts)?;
+                        quotes.push((swap, amount_in, amount_out));
+                        next_in = Some(amount_in);
+                    }
+
+                    if quotes.len() == sequence.len() {
+                        total = total.saturating_add(next_in.unwrap_or_default());
+                    } else {
+                        complete = false;
+                    }
+
+                    for (swap, amount_in, amount_out) in quotes.into_iter().rev() {
+                        if let Some(pool) = pools.get_mut(&swap.pool) {
+                            pool.swap(swap.token_in, amount_in, swap.token_out, amount_out)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        match self.amounts {
+            SwapAmounts::ExactIn { min_amount_out, .. } if complete && total < min_amount_out => {
+                None
+            }
+            SwapAmounts::ExactOut { max_amount_in, .. } if complete && total > max_amount_in => {
+                None
+            }
+            _ => Some(()),
+        }
+    }
+}
+
+impl Debug for BalancerBatchSwap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}({})", self.method, symbols(&self.tokens))
+    }
+}
+
+const AGGREGATOR_SWAPS: &[&str] = &["swap", "unoswap", "transformERC20", "sellToUniswap"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AggregatorSwapMethod {
+    OneInchSwap,
+    OneInchUnoswap,
+    ZeroExTransformERC20,
+}
+
+/// Swaps routed by aggregators through venues that can't be told from the
+/// calldata, so they are only known by the tokens they trade.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AggregatorSwap {
+    pub method: AggregatorSwapMethod,
+    pub token_in: H160,
+    /// Unoswaps only name the pairs they go through, not the token bought.
+    pub token_out: Option<H160>,
+    pub tokens: [Option<Token>; 2],
+}
+
+impl AggregatorSwap {
+    fn parse_kind(
+        tx: &Transaction,
+        protocol: Protocol,
+        routers: &Routers,
+        tokens: &HashMap<H160, Token>,
+    ) -> Option<Kind> {
+        let (function, args) = calldata::decode(protocol.abi(), AGGREGATOR_SWAPS, &tx.input.0)?;
+
+        let (method, token_in, token_out) = match function.name.as_str() {
+            "swap" => {
+                let (_caller, desc, _data): (H160, abi::Token, abi::Token) = calldata::args(args)?;
+                let (token_in, token_out, _, _, _amount, _min_return, _flags, _permit): (
+                    H160,
+                    H160,
+                    H160,
+                    H160,
+                    U256,
+                    U256,
+                    U256,
+                    abi::Token,
+                ) = calldata::tuple(desc)?;
+
+                (AggregatorSwapMethod::OneInchSwap, token_in, Some(token_out))
+            }
+            "unoswap" => {
+                let (token_in, _amount, _min_return, _pools): (H160, U256, U256, abi::Token) =
+                    calldata::args(args)?;
+
+                (AggregatorSwapMethod::OneInchUnoswap, token_in, None)
+            }
+            "transformERC20" => {
+                let (token_in, token_out, _amount, _min_amount, _transformations): (
+                    H160,
+                    H160,
+                    U256,
+                    U256,
+                    abi::Token,
+                ) = calldata::args(args)?;
+
+                let method = AggregatorSwapMethod::ZeroExTransformERC20;
+                (method, token_in, Some(token_out))
+            }
+            "sellToUniswap" => {
+                let (path, amount_in, min_amount_out, is_sushi): (Vec<H160>, U256, U256, bool) =
+                    calldata::args(args)?;
+
+                let protocol = if is_sushi {
+                    Protocol::Sushiswap
+                } else {
+                    Protocol::Uniswap
+                };
+                let amounts = SwapAmounts::ExactIn {
+                    amount_in,
+                    min_amount_out,
+                };
+                let path = path.into_iter().map(|t| routers.token(t)).collect();
+                let method = UniswapSwapMethod::SellToUniswap;
+                return UniswapSwap::new_kind(protocol, method, amounts, path, tokens);
+            }
+            _ => return None,
+        };
+
+        let token_in = routers.token(token_in);
+        let token_out = token_out.map(|t| routers.token(t));
+        let tokens = [
+            tokens.get(&token_in).cloned(),
+            token_out.and_then(|t| tokens.get(&t).cloned()),
+        ];
+        if let [None, None] = tokens {
+            return None;
+        }
+
+        Some(Kind::AggregatorSwap(AggregatorSwap {
+            token_out,
+            token_in,
+            tokens,
+            method,
+        }))
+    }
+
+    fn conflicts(&self, token_from: H160, token_to: H160) -> bool {
+        self.token_in == token_from && self.token_out.unwrap_or(token_to) == token_to
+    }
+}
+
+impl Debug for AggregatorSwap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}({})", self.method, symbols(&self.tokens))
+    }
+}
+
+/// Calls into a registered competitor that don't decode as anything else,
+/// assumed to be going after the same opportunities as us.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitorOperation;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethcontract::common::FunctionExt;
+    use std::str::FromStr;
+    use web3::types::Bytes;
+
+    fn transaction(to: H160, selector: [u8; 4], words: &[U256], value: U256) -> Transaction {
+        let mut input = selector.to_vec();
+        for word in words {
+            let mut buffer = [0; 32];
+            word.to_big_endian(&mut buffer);
+            input.extend_from_slice(&buffer);
+        }
+
+        raw_transaction(to, input, value)
+    }
+
+    fn raw_transaction(to: H160, input: Vec<u8>, value: U256) -> Transaction {
+        Transaction {
+            hash: H256::zero(),
+            nonce: U256::zero(),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            from: H160::zero(),
+            to: Some(to),
+            value,
+            gas_price: U256::exp10(9),
+            gas: U256::zero(),
+            input: Bytes(input),
+            raw: None,
+        }
+    }
+
+    /// Uniswap's router at 1 and the rest of the protocols from 5 on, with
+    /// the wrapped native token at 3.
+    fn routers() -> Routers {
+        let protocols = [
+            (1, Protocol::Uniswap),
+            (5, Protocol::Sushiswap),
+            (6, Protocol::OneInch),
+            (7, Protocol::ZeroEx),
+            (8, Protocol::BalancerExchangeProxy),
+        ];
+
+        Routers {
+            addresses: protocols
+                .iter()
+                .map(|&(address, protocol)| (H160::from_low_u64_be(address), protocol))
+                .collect(),
+            aggregators: HashSet::new(),
+            wrapped_native: H160::from_low_u64_be(3),
+        }
+    }
+
+    fn call(protocol: Protocol, name: &str, args: &[abi::Token]) -> Vec<u8> {
+        let function = protocol.abi().function(name).unwrap();
+        function.encode_input(args).unwrap()
+    }
+
+    fn token(address: H160) -> Token {
+        Token {
+            address,
+            symbol: format!("{:?}", address),
+            decimals: 18,
+            weth_uniswap_pair: None,
+        }
+    }
+
+    #[test]
+    fn applies_uniswap_swap() {
+        let router = H160::from_low_u64_be(1);
+        let pair = H160::from_low_u64_be(2);
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+
+        let amount_in = U256::exp10(18);
+        let tx = transaction(
+            router,
+            [0x7f, 0xf3, 0x6a, 0xb5],
+            &[
+                U256::zero(),
+                U256::from(0x80),
+                U256::zero(),
+                U256::zero(),
+                U256::from(2),
+                U256::from(H256::from(weth).as_bytes()),
+                U256::from(H256::from(dai).as_bytes()),
+            ],
+            amount_in,
+        );
+
+        let pending = PendingTx::from_transaction(
+            &tx,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &tokens,
+        )
+        .unwrap();
+        assert!(pending.conflicts(weth, dai, H160::zero(), H160::zero()));
+        assert!(!pending.conflicts(dai, weth, H160::zero(), H160::zero()));
+
+        let sushiswap = PendingTx::from_transaction(
+            &Transaction {
+                to: Some(H160::from_low_u64_be(5)),
+                ..tx.clone()
+            },
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &tokens,
+        )
+        .unwrap();
+        assert!(!sushiswap.conflicts(weth, dai, H160::zero(), H160::zero()));
+
+        let json = serde_json::to_value(&pending).unwrap();
+        let swap = &json["kind"]["UniswapSwap"];
+        assert_eq!(swap["method"], "ExactETHForTokens");
+        assert_eq!(swap["protocol"], "Uniswap");
+        assert_eq!(swap["path"], serde_json::json!([weth, dai]));
+        assert_eq!(swap["tokens"][1]["symbol"], token(dai).symbol);
+        let decoded: PendingTx = serde_json::from_value(json).unwrap();
+        assert!(matches!(decoded.kind, Kind::UniswapSwap(swap) if swap.path == vec![weth, dai]));
+
+        let reserve0 = U256::exp10(21);
+        let reserve1 = U256::exp10(24);
+
+        let mut pairs = vec![(
+            pair,
+            UniswapPairResolved {
+                token0: weth,
+                reserve0,
+                reserve1,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let pair_index = vec![((weth, dai), pair), ((dai, weth), pair)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(pending.impact(&pairs, &HashMap::new(), &pair_index), 10);
+        pending.apply(&mut pairs, &mut HashMap::new(), &pair_index);
+
+        let amount_out = uniswap_out_given_in(reserve0, reserve1, amount_in);
+        assert_eq!(pairs[&pair].reserve0, reserve0 + amount_in);
+        assert_eq!(pairs[&pair].reserve1, reserve1 - amount_out);
+
+        // Amounts decoded against stale state leave the reserves as they are.
+        let swapped = pairs[&pair].clone();
+        let resolved = pairs.get_mut(&pair).unwrap();
+        assert!(resolved.swap(weth, U256::MAX, U256::zero()).is_none());
+        assert!(resolved
+            .swap(dai, U256::one(), swapped.reserve0 + 1)
+            .is_none());
+        assert_eq!(*resolved, swapped);
+    }
+
+    /// Deterministic xorshift generator, enough to fuzz the decoding.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        /// Words biased towards what the decoder interprets: small offsets
+        /// and lengths, huge lengths, known addresses and plain noise.
+        fn word(&mut self, addresses: &[H160]) -> [u8; 32] {
+            let mut word = [0; 32];
+            match self.below(5) {
+                0 => U256::from(32 * self.below(16)).to_big_endian(&mut word),
+                1 => U256::from(self.below(8)).to_big_endian(&mut word),
+                2 => U256::from(u32::MAX - self.below(4) as u32).to_big_endian(&mut word),
+                3 => {
+                    let address = addresses[self.below(addresses.len() as u64) as usize];
+                    word[12..].copy_from_slice(address.as_bytes());
+                }
+                _ => word.iter_mut().for_each(|b| *b = self.next() as u8),
+            }
+            word
+        }
+    }
+
+    #[test]
+    fn decodes_balancer_swap() {
+        let pool = H160::from_low_u64_be(1);
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+        let pools = vec![pool].into_iter().collect();
+
+        let words = [
+            U256::from(H256::from(dai).as_bytes()),
+            U256::exp10(20),
+            U256::from(H256::from(weth).as_bytes()),
+            U256::exp10(18),
+            U256::MAX,
+        ];
+
+        let tx = transaction(pool, [0x7c, 0x5e, 0x9e, 0xa4], &words, U256::zero());
+        let pending = PendingTx::from_transaction(
+            &tx,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &pools,
+            &tokens,
+        )
+        .unwrap();
+        assert!(pending.conflicts(dai, H160::zero(), H160::zero(), pool));
+        assert!(!pending.conflicts(weth, dai, H160::zero(), pool));
+
+        match pending.kind {
+            Kind::BalancerSwap(swap) => match swap.amounts {
+                SwapAmounts::ExactOut {
+                    amount_out,
+                    max_amount_in,
+                } => {
+                    assert_eq!(amount_out, U256::exp10(18));
+                    assert_eq!(max_amount_in, U256::exp10(20));
+                }
+                amounts => panic!("unexpected amounts {:?}", amounts),
+            },
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+
+        let mut input = tx.input.0.clone();
+        input.push(0);
+        let tx = raw_transaction(pool, input, U256::zero());
+        assert!(PendingTx::from_transaction(
+            &tx,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &pools,
+            &tokens
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn rejects_hostile_lengths() {
+        let router = H160::from_low_u64_be(1);
+        let weth = H160::from_low_u64_be(3);
+        let tokens = vec![(weth, token(weth))].into_iter().collect();
+
+        for len in &[U256::from(u32::MAX), U256::from(3), U256::MAX] {
+            let tx = transaction(
+                router,
+                [0x7f, 0xf3, 0x6a, 0xb5],
+                &[
+                    U256::zero(),
+                    U256::from(0x80),
+                    U256::zero(),
+                    U256::zero(),
+                    *len,
+                    U256::from(H256::from(weth).as_bytes()),
+                ],
+                U256::zero(),
+            );
+
+            assert!(PendingTx::from_transaction(
+                &tx,
+                &routers(),
+                &Competitors::default(),
+                &HashSet::new(),
+                &HashSet::new(),
+                &tokens
+            )
+            .is_none());
         }
     }
+
+    #[test]
+    fn survives_arbitrary_calldata() {
+        let router = H160::from_low_u64_be(1);
+        let pool = H160::from_low_u64_be(2);
+        let addresses = [H160::zero(), router, pool, H160::from_low_u64_be(3)];
+        let targets: Vec<_> = routers()
+            .addresses
+            .keys()
+            .copied()
+            .chain(vec![pool])
+            .collect();
+
+        let tokens = addresses[3..].iter().map(|&a| (a, token(a))).collect();
+        let pools = vec![pool].into_iter().collect();
+
+        let protocols = [
+            Protocol::Uniswap,
+            Protocol::OneInch,
+            Protocol::ZeroEx,
+            Protocol::BalancerExchangeProxy,
+        ];
+        let selectors: Vec<_> = protocols
+            .iter()
+            .flat_map(|protocol| protocol.abi().functions())
+            .chain(BalancerPool::artifact().abi.functions())
+            .map(|function| function.selector())
+            .collect();
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20_000 {
+            let to = targets[rng.below(targets.len() as u64) as usize];
+            let mut input = selectors[rng.below(selectors.len() as u64) as usize].to_vec();
+            for _ in 0..rng.below(16) {
+                input.extend_from_slice(&rng.word(&addresses));
+            }
+            if rng.below(8) == 0 {
+                input.truncate(rng.below(input.len() as u64 + 1) as usize);
+            }
+
+            let tx = raw_transaction(to, input, U256::from(rng.next()));
+            PendingTx::from_transaction(
+                &tx,
+                &routers(),
+                &Competitors::default(),
+                &HashSet::new(),
+                &pools,
+                &tokens,
+            );
+        }
+    }
+
+    #[test]
+    fn survives_mutated_calldata() {
+        let router = H160::from_low_u64_be(1);
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+
+        let valid = transaction(
+            router,
+            [0x38, 0xed, 0x17, 0x39],
+            &[
+                U256::exp10(18),
+                U256::zero(),
+                U256::from(0xa0),
+                U256::zero(),
+                U256::zero(),
+                U256::from(2),
+                U256::from(H256::from(weth).as_bytes()),
+                U256::from(H256::from(dai).as_bytes()),
+            ],
+            U256::zero(),
+        );
+        assert!(PendingTx::from_transaction(
+            &valid,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &tokens
+        )
+        .is_some());
+
+        let input = &valid.input.0;
+        for len in 0..input.len() {
+            let tx = raw_transaction(router, input[..len].to_vec(), U256::zero());
+            assert!(PendingTx::from_transaction(
+                &tx,
+                &routers(),
+                &Competitors::default(),
+                &HashSet::new(),
+                &HashSet::new(),
+                &tokens
+            )
+            .is_none());
+        }
+
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for index in 4..input.len() {
+            for _ in 0..8 {
+                let mut input = input.clone();
+                input[index] = rng.next() as u8;
+
+                let tx = raw_transaction(router, input, U256::zero());
+                PendingTx::from_transaction(
+                    &tx,
+                    &routers(),
+                    &Competitors::default(),
+                    &HashSet::new(),
+                    &HashSet::new(),
+                    &tokens,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_known_selectors() {
+        let selectors = [
+            (
+                Protocol::Uniswap,
+                "swapExactTokensForTokensSupportingFeeOnTransferTokens",
+                [0x5c, 0x11, 0xd7, 0x95],
+            ),
+            (
+                Protocol::Uniswap,
+                "swapExactETHForTokensSupportingFeeOnTransferTokens",
+                [0xb6, 0xf9, 0xde, 0x95],
+            ),
+            (
+                Protocol::Uniswap,
+                "swapExactTokensForETHSupportingFeeOnTransferTokens",
+                [0x79, 0x1a, 0xc9, 0x47],
+            ),
+            (Protocol::OneInch, "swap", [0x7c, 0x02, 0x52, 0x00]),
+            (Protocol::OneInch, "unoswap", [0x2e, 0x95, 0xb6, 0xc8]),
+            (Protocol::ZeroEx, "sellToUniswap", [0xd9, 0x62, 0x7a, 0xa4]),
+            (Protocol::ZeroEx, "transformERC20", [0x41, 0x55, 0x65, 0xb0]),
+            (
+                Protocol::BalancerExchangeProxy,
+                "batchSwapExactIn",
+                [0x87, 0x43, 0xad, 0x58],
+            ),
+            (
+                Protocol::BalancerExchangeProxy,
+                "multihopBatchSwapExactIn",
+                [0xe2, 0xb3, 0x97, 0x46],
+            ),
+        ];
+
+        for (protocol, name, selector) in &selectors {
+            assert_eq!(
+                protocol.abi().function(name).unwrap().selector(),
+                *selector,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_aggregator_swaps() {
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+        let native = H160::from_str(crate::routers::NATIVE_PLACEHOLDER).unwrap();
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+
+        let desc = abi::Token::Tuple(vec![
+            abi::Token::Address(native),
+            abi::Token::Address(dai),
+            abi::Token::Address(H160::zero()),
+            abi::Token::Address(H160::zero()),
+            abi::Token::Uint(U256::exp10(18)),
+            abi::Token::Uint(U256::one()),
+            abi::Token::Uint(U256::zero()),
+            abi::Token::Bytes(vec![]),
+        ]);
+        let input = call(
+            Protocol::OneInch,
+            "swap",
+            &[
+                abi::Token::Address(H160::zero()),
+                desc,
+                abi::Token::Bytes(vec![1, 2, 3]),
+            ],
+        );
+
+        let tx = raw_transaction(H160::from_low_u64_be(6), input, U256::exp10(18));
+        let pending = PendingTx::from_transaction(
+            &tx,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &tokens,
+        )
+        .unwrap();
+        assert!(pending.conflicts(weth, dai, H160::zero(), H160::zero()));
+        assert!(!pending.conflicts(dai, weth, H160::zero(), H160::zero()));
+
+        let sell_to_uniswap = |is_sushi| {
+            let input = call(
+                Protocol::ZeroEx,
+                "sellToUniswap",
+                &[
+                    abi::Token::Array(vec![abi::Token::Address(dai), abi::Token::Address(native)]),
+                    abi::Token::Uint(U256::exp10(18)),
+                    abi::Token::Uint(U256::one()),
+                    abi::Token::Bool(is_sushi),
+                ],
+            );
+
+            let tx = raw_transaction(H160::from_low_u64_be(7), input, U256::zero());
+            PendingTx::from_transaction(
+                &tx,
+                &routers(),
+                &Competitors::default(),
+                &HashSet::new(),
+                &HashSet::new(),
+                &tokens,
+            )
+            .unwrap()
+        };
+
+        let pending = sell_to_uniswap(false);
+        assert!(pending.conflicts(dai, weth, H160::zero(), H160::zero()));
+
+        // Sushiswap pairs aren't ours to conflict with.
+        let pending = sell_to_uniswap(true);
+        assert!(!pending.conflicts(dai, weth, H160::zero(), H160::zero()));
+
+        match &pending.kind {
+            Kind::UniswapSwap(swap) => assert_eq!(swap.protocol, Protocol::Sushiswap),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn applies_balancer_batch_swap() {
+        let proxy = H160::from_low_u64_be(8);
+        let pool0 = H160::from_low_u64_be(10);
+        let pool1 = H160::from_low_u64_be(11);
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+        let usdc = H160::from_low_u64_be(5);
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+
+        let swap = |pool, token_in, token_out, amount, limit| {
+            abi::Token::Tuple(vec![
+                abi::Token::Address(pool),
+                abi::Token::Address(token_in),
+                abi::Token::Address(token_out),
+                abi::Token::Uint(amount),
+                abi::Token::Uint(limit),
+                abi::Token::Uint(U256::MAX),
+            ])
+        };
+
+        let amount_in = U256::exp10(18);
+        let input = call(
+            Protocol::BalancerExchangeProxy,
+            "multihopBatchSwapExactIn",
+            &[
+                abi::Token::Array(vec![abi::Token::Array(vec![
+                    swap(pool0, weth, usdc, amount_in, U256::zero()),
+                    swap(pool1, usdc, dai, U256::zero(), U256::zero()),
+                ])]),
+                abi::Token::Address(weth),
+                abi::Token::Address(dai),
+                abi::Token::Uint(amount_in),
+                abi::Token::Uint(U256::one()),
+            ],
+        );
+
+        let tx = raw_transaction(proxy, input, U256::zero());
+        let pending = PendingTx::from_transaction(
+            &tx,
+            &routers(),
+            &Competitors::default(),
+            &HashSet::new(),
+            &HashSet::new(),
+            &tokens,
+        )
+        .unwrap();
+        assert!(pending.conflicts(weth, H160::zero(), H160::zero(), pool0));
+        assert!(!pending.conflicts(weth, H160::zero(), H160::zero(), pool1));
+
+        let balance = U256::exp10(21);
+        let resolved = |token_in, token_out| BalancerPoolResolved {
+            balances: vec![(token_in, balance), (token_out, balance)]
+                .into_iter()
+                .collect(),
+            swap_fee: U256::exp10(15),
+        };
+        let mut pools = vec![(pool0, resolved(weth, usdc)), (pool1, resolved(usdc, dai))]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        pending.apply(&mut HashMap::new(), &mut pools, &HashMap::new());
+
+        let fee = U256::exp10(15);
+        let usdc_out = balancer_out_given_in(balance, balance, fee, amount_in);
+        let dai_out = balancer_out_given_in(balance, balance, fee, usdc_out);
+        assert_eq!(pools[&pool0].balances[&weth], balance + amount_in);
+        assert_eq!(pools[&pool0].balances[&usdc], balance - usdc_out);
+        assert_eq!(pools[&pool1].balances[&usdc], balance + usdc_out);
+        assert_eq!(pools[&pool1].balances[&dai], balance - dai_out);
+    }
+
+    #[test]
+    fn detects_liquidity_and_pair_swaps() {
+        let router = H160::from_low_u64_be(1);
+        let pair = H160::from_low_u64_be(2);
+        let weth = H160::from_low_u64_be(3);
+        let dai = H160::from_low_u64_be(4);
+        let pool = H160::from_low_u64_be(10);
+        let tokens = vec![(weth, token(weth)), (dai, token(dai))]
+            .into_iter()
+            .collect();
+        let pairs_set = vec![pair].into_iter().collect();
+        let pools_set = vec![pool].into_iter().collect();
+
+        let reserve0 = U256::exp10(21);
+        let reserve1 = U256::exp10(24);
+        let resolved = || {
+            vec![(
+                pair,
+                UniswapPairResolved {
+                    token0: weth,
+                    reserve0,
+                    reserve1,
+                },
+            )]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+        };
+        let pair_index = vec![((weth, dai), pair), ((dai, weth), pair)]
+            .into_iter()
+            .collect();
+
+        let parse = |to, input| {
+            let tx = raw_transaction(to, input, U256::exp10(18));
+            PendingTx::from_transaction(
+                &tx,
+                &routers(),
+                &Competitors::default(),
+                &pairs_set,
+                &pools_set,
+                &tokens,
+            )
+            .unwrap()
+        };
+
+        let amount_out = U256::exp10(20);
+        let input = UniswapPair::artifact()
+            .abi
+            .function("swap")
+            .unwrap()
+            .encode_input(&[
+                abi::Token::Uint(U256::zero()),
+                abi::Token::Uint(amount_out),
+                abi::Token::Address(H160::zero()),
+                abi::Token::Bytes(vec![]),
+            ])
+            .unwrap();
+
+        let pending = parse(pair, input);
+        assert!(pending.conflicts(H160::zero(), H160::zero(), pair, H160::zero()));
+        assert!(!pending.conflicts(weth, dai, H160::zero(), pool));
+
+        let mut pairs = resolved();
+        pending.apply(&mut pairs, &mut HashMap::new(), &pair_index);
+        let amount_in = uniswap_in_given_out(reserve0, reserve1, amount_out);
+        assert_eq!(pairs[&pair].reserve0, reserve0 + amount_in);
+        assert_eq!(pairs[&pair].reserve1, reserve1 - amount_out);
+
+        let input = call(
+            Protocol::Uniswap,
+            "addLiquidityETH",
+            &[
+                abi::Token::Address(dai),
+                abi::Token::Uint(U256::exp10(24)),
+                abi::Token::Uint(U256::zero()),
+                abi::Token::Uint(U256::zero()),
+                abi::Token::Address(H160::zero()),
+                abi::Token::Uint(U256::zero()),
+            ],
+        );
+
+        let pending = parse(router, input);
+        assert!(pending.conflicts(weth, dai, H160::zero(), H160::zero()));
+
+        let mut pairs = resolved();
+        pending.apply(&mut pairs, &mut HashMap::new(), &pair_index);
+        assert_eq!(pairs[&pair].reserve0, reserve0 + U256::exp10(18));
+        assert_eq!(pairs[&pair].reserve1, reserve1 + U256::exp10(21));
+
+        // The router reverts when only one of the reserves is empty.
+        let mut pairs = resolved();
+        pairs.get_mut(&pair).unwrap().reserve1 = U256::zero();
+        pending.apply(&mut pairs, &mut HashMap::new(), &pair_index);
+        assert_eq!(pairs[&pair].reserve0, reserve0);
+        assert_eq!(pairs[&pair].reserve1, U256::zero());
+
+        // Nor are additions past what the pair's reserves can hold.
+        for desired in [U256::MAX, U256::one() << 120] {
+            let input = call(
+                Protocol::Uniswap,
+                "addLiquidity",
+                &[
+                    abi::Token::Address(weth),
+                    abi::Token::Address(dai),
+                    abi::Token::Uint(desired),
+                    abi::Token::Uint(desired),
+                    abi::Token::Uint(U256::zero()),
+                    abi::Token::Uint(U256::zero()),
+                    abi::Token::Address(H160::zero()),
+                    abi::Token::Uint(U256::zero()),
+                ],
+            );
+            let pending = parse(router, input);
+
+            for empty in [false, true] {
+                let mut pairs = resolved();
+                if empty {
+                    let pair = pairs.get_mut(&pair).unwrap();
+                    pair.reserve0 = U256::zero();
+                    pair.reserve1 = U256::zero();
+                }
+                let before = pairs.clone();
+                pending.apply(&mut pairs, &mut HashMap::new(), &pair_index);
+                assert_eq!(pairs, before);
+            }
+        }
+
+        let input = BalancerPool::artifact()
+            .abi
+            .function("exitPool")
+            .unwrap()
+            .encode_input(&[
+                abi::Token::Uint(U256::one()),
+                abi::Token::Array(vec![abi::Token::Uint(U256::zero())]),
+            ])
+            .unwrap();
+
+        let pending = parse(pool, input);
+        assert!(pending.conflicts(H160::zero(), H160::zero(), H160::zero(), pool));
+        assert!(!pending.conflicts(weth, dai, pair, H160::zero()));
+    }
 }
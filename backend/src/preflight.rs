@@ -0,0 +1,156 @@
+use ethcontract::{
+    common::abi::{decode, ParamType, Token},
+    errors::ExecutionError,
+    BlockNumber,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use web3::{
+    types::{Bytes, CallRequest, H160, U256},
+    Error, Transport, Web3,
+};
+
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Why a simulated perform reverted, by the contract's require messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Revert {
+    UniswapReservesMismatch,
+    BalancerBalancesMismatch,
+    PaybackFailed,
+    Other(Option<String>),
+}
+
+impl Revert {
+    pub fn from_reason(reason: Option<String>) -> Revert {
+        match reason.as_deref() {
+            Some("Uniswap reserves mismatch") => Revert::UniswapReservesMismatch,
+            Some("Balancer balances mismatch") => Revert::BalancerBalancesMismatch,
+            Some("Payback failed") => Revert::PaybackFailed,
+            _ => Revert::Other(reason),
+        }
+    }
+
+    /// The revert behind the error, if it is one. Geth nodes report them as
+    /// rpc errors, with the abi encoded reason as data when there is one.
+    pub fn from_error(err: &ExecutionError) -> Option<Revert> {
+        match err {
+            ExecutionError::Revert(reason) => Some(Revert::from_reason(reason.clone())),
+            ExecutionError::Web3(Error::Rpc(err)) => {
+                let reason = err.data.clone().and_then(decode_reason);
+
+                match err.message.strip_prefix("execution reverted") {
+                    Some(message) => {
+                        let message = message.strip_prefix(": ").map(str::to_owned);
+                        Some(Revert::from_reason(reason.or(message)))
+                    }
+                    None => reason.map(|reason| Revert::from_reason(Some(reason))),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a hex encoded `Error(string)` revert.
+fn decode_reason(data: Value) -> Option<String> {
+    let Bytes(bytes) = serde_json::from_value(data).ok()?;
+    if bytes.get(..4)? != ERROR_SELECTOR {
+        return None;
+    }
+
+    match decode(&[ParamType::String], &bytes[4..]).ok()?.pop()? {
+        Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
+
+fn request(from: H160, to: H160, data: Bytes) -> CallRequest {
+    CallRequest {
+        from: Some(from),
+        to: Some(to),
+        gas: None,
+        gas_price: None,
+        value: None,
+        data: Some(data),
+    }
+}
+
+/// Runs the call with `eth_call` on top of the given block. Replaying a mined
+/// transaction on top of the block before its own tells why it reverted, as
+/// long as the ones ahead of it in its block don't matter.
+pub async fn call<T: Transport>(
+    web3: &Web3<T>,
+    from: H160,
+    to: H160,
+    data: Bytes,
+    block: BlockNumber,
+) -> Result<Bytes, ExecutionError> {
+    let output = web3
+        .eth()
+        .call(request(from, to, data), Some(block.into()))
+        .await?;
+    Ok(output)
+}
+
+/// Runs the call with `eth_call` and then `eth_estimateGas` on top of the
+/// given block, returning the gas it would use.
+pub async fn simulate<T: Transport>(
+    web3: &Web3<T>,
+    from: H160,
+    to: H160,
+    data: Bytes,
+    block: BlockNumber,
+) -> Result<U256, ExecutionError> {
+    call(web3, from, to, data.clone(), block).await?;
+
+    let gas = web3
+        .eth()
+        .estimate_gas(request(from, to, data), Some(block))
+        .await?;
+    Ok(gas)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethcontract::common::abi::encode;
+
+    fn rpc_error(message: &str, data: Option<Value>) -> ExecutionError {
+        ExecutionError::Web3(Error::Rpc(jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::ServerError(3),
+            message: message.to_owned(),
+            data,
+        }))
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(encode(&[Token::String(
+            "Uniswap reserves mismatch".to_owned(),
+        )]));
+        let data = serde_json::to_value(Bytes(data)).unwrap();
+
+        assert_eq!(
+            Revert::from_error(&rpc_error("execution reverted", Some(data))),
+            Some(Revert::UniswapReservesMismatch)
+        );
+        assert_eq!(
+            Revert::from_error(&rpc_error(
+                "execution reverted: Balancer balances mismatch",
+                None
+            )),
+            Some(Revert::BalancerBalancesMismatch)
+        );
+        assert_eq!(
+            Revert::from_error(&rpc_error("execution reverted", None)),
+            Some(Revert::Other(None))
+        );
+        assert_eq!(
+            Revert::from_error(&ExecutionError::Revert(Some("Payback failed".to_owned()))),
+            Some(Revert::PaybackFailed)
+        );
+        assert_eq!(Revert::from_error(&rpc_error("nonce too low", None)), None);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{latest_block::Head, state::StateCache};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use web3::types::Transaction;
+
+/// Something watch_pairs reacts to, as seen at the time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Event {
+    /// The state cache as read from the node, when starting and whenever
+    /// parts of it were read again.
+    Snapshot(Box<StateCache>),
+    NewHead(Box<Head>),
+    PendingTx(Box<Transaction>),
+}
+
+/// An event along with the milliseconds since the epoch it was seen at.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Record {
+    pub timestamp: u64,
+    pub event: Event,
+}
+
+/// Appends events to a recording file, one json record per line.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn open(path: &str) -> Result<Recorder, Box<dyn Error>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file })
+    }
+
+    pub fn record(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let mut line = serde_json::to_string(&Record {
+            event: event.clone(),
+            timestamp,
+        })?;
+        line.push('\n');
+
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+pub fn read(path: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        records.push(serde_json::from_str(&line?)?);
+    }
+
+    Ok(records)
+}
+
+/// Yields the recorded events keeping their original spacing, divided by the
+/// given speed, which has to be positive. An infinite speed yields them as fast
+/// as they are consumed.
+pub fn replay(records: Vec<Record>, speed: f64) -> impl Stream<Item = Event> {
+    assert!(speed > 0.0, "replay speed must be positive");
+
+    let first = records.first().map_or(0, |r| r.timestamp);
+    let start = Instant::now();
+
+    futures::stream::iter(records).then(move |record| async move {
+        if speed.is_finite() {
+            let elapsed = record.timestamp.saturating_sub(first) as f64 / 1000.0 / speed;
+            tokio::time::delay_until((start + Duration::from_secs_f64(elapsed)).into()).await;
+        }
+
+        record.event
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use web3::types::{H256, U64};
+
+    fn head(number: u64) -> Event {
+        Event::NewHead(Box::new(Head {
+            number: number.into(),
+            hash: H256::from_low_u64_be(number),
+            parent_hash: H256::from_low_u64_be(number - 1),
+            gas_price: 1.into(),
+            accounts: vec![],
+            logs: vec![],
+            transactions: vec![],
+        }))
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_events() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let state = StateCache {
+            pairs: HashMap::new(),
+            pools: HashMap::new(),
+            number: 1.into(),
+            hash: H256::from_low_u64_be(1),
+            stale_pools: HashSet::new(),
+        };
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(1),
+            ..Transaction::default()
+        };
+        let mut recorder = Recorder::open(path).unwrap();
+        recorder.record(&Event::Snapshot(Box::new(state))).unwrap();
+        recorder.record(&head(1)).unwrap();
+        recorder.record(&Event::PendingTx(Box::new(tx))).unwrap();
+        recorder.record(&head(2)).unwrap();
+
+        let mut records = read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(records.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        // Recordings appended to across runs may go back in time.
+        records[3].timestamp = 0;
+
+        let number = |event: &Event| match event {
+            Event::NewHead(head) => Some(head.number),
+            _ => None,
+        };
+        let events: Vec<_> = replay(records, 1000.0).collect().await;
+        assert!(matches!(&events[0], Event::Snapshot(state) if state.number == U64::from(1)));
+        assert_eq!(number(&events[1]), Some(1.into()));
+        assert!(matches!(&events[2], Event::PendingTx(tx) if tx.hash == H256::from_low_u64_be(1)));
+        assert_eq!(number(&events[3]), Some(2.into()));
+    }
+}
@@ -0,0 +1,251 @@
+use ethcontract::PrivateKey;
+use jsonrpc_core::{Output, Value};
+use serde::{Deserialize, Deserializer, Serialize};
+use web3::{
+    error::{Error, Result},
+    helpers,
+    signing::{keccak256, Key},
+    types::{Bytes, H256, U256, U64},
+};
+
+const SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleParams<'a> {
+    txs: &'a [Bytes],
+    block_number: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_block_number: Option<&'a str>,
+}
+
+/// How a transaction of a bundle went in a relay simulation.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedTx {
+    pub tx_hash: H256,
+    pub gas_used: u64,
+    pub error: Option<String>,
+    pub revert: Option<String>,
+}
+
+/// A bundle simulated on top of the latest block. Wei amounts come as decimal
+/// strings.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSimulation {
+    #[serde(deserialize_with = "decimal")]
+    pub bundle_gas_price: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub coinbase_diff: U256,
+    pub total_gas_used: u64,
+    pub results: Vec<SimulatedTx>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SentBundle {
+    bundle_hash: H256,
+}
+
+fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<U256, D::Error> {
+    let string = String::deserialize(deserializer)?;
+    U256::from_dec_str(&string).map_err(serde::de::Error::custom)
+}
+
+/// A Flashbots-style relay taking bundles of signed transactions privately,
+/// authenticating requests with the given key.
+#[derive(Clone)]
+pub struct Relay {
+    client: reqwest::Client,
+    url: String,
+    key: PrivateKey,
+}
+
+impl Relay {
+    pub fn new(url: String, key: PrivateKey) -> Relay {
+        Relay {
+            client: reqwest::Client::new(),
+            url,
+            key,
+        }
+    }
+
+    /// Runs the bundle with `eth_callBundle` as if it were in the given block.
+    pub async fn simulate(&self, txs: &[Bytes], block_number: U64) -> Result<BundleSimulation> {
+        let params = BundleParams {
+            txs,
+            block_number,
+            state_block_number: Some("latest"),
+        };
+
+        helpers::decode(self.request("eth_callBundle", params).await?)
+    }
+
+    /// Submits the bundle with `eth_sendBundle` for inclusion in the given block.
+    pub async fn send(&self, txs: &[Bytes], block_number: U64) -> Result<H256> {
+        let params = BundleParams {
+            txs,
+            block_number,
+            state_block_number: None,
+        };
+
+        let sent: SentBundle = helpers::decode(self.request("eth_sendBundle", params).await?)?;
+        Ok(sent.bundle_hash)
+    }
+
+    async fn request(&self, method: &str, params: BundleParams<'_>) -> Result<Value> {
+        let request = helpers::build_request(1, method, vec![helpers::serialize(&params)]);
+        let body = helpers::to_string(&request);
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(SIGNATURE_HEADER, self.signature(body.as_bytes())?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?
+            .bytes()
+            .await
+            .map_err(|err| Error::Transport(err.to_string()))?;
+
+        let output: Output =
+            serde_json::from_slice(&response).map_err(|err| Error::Decoder(err.to_string()))?;
+        helpers::to_result_from_output(output)
+    }
+
+    /// `<address>:<signature>` of the personal message holding the hex encoded
+    /// hash of the body.
+    fn signature(&self, body: &[u8]) -> Result<String> {
+        let signature = self
+            .key
+            .sign(&personal_message_hash(body), None)
+            .map_err(|_| Error::Internal)?;
+
+        let mut bytes = signature.r.as_bytes().to_vec();
+        bytes.extend(signature.s.as_bytes());
+        bytes.push(signature.v as u8);
+
+        Ok(format!(
+            "{:?}:0x{}",
+            self.key.public_address(),
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        ))
+    }
+}
+
+fn personal_message_hash(body: &[u8]) -> [u8; 32] {
+    let message = format!("{:?}", H256(keccak256(body)));
+    let mut bytes = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    bytes.extend(message.as_bytes());
+    keccak256(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server,
+    };
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+    use web3::types::H160;
+
+    /// Signer and method of each request.
+    type Requests = Arc<Mutex<Vec<(H160, String)>>>;
+
+    /// Answers bundle requests the way a relay would, keeping track of them.
+    fn stand_in_relay() -> (String, Requests) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let seen = requests.clone();
+
+        let make_service = make_service_fn(move |_| {
+            let seen = seen.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let seen = seen.clone();
+                    async move {
+                        let header = request.headers()[SIGNATURE_HEADER].to_str().unwrap();
+                        let (_, signature) = header.split_at(header.find(':').unwrap() + 1);
+                        let signature: Bytes =
+                            serde_json::from_value(Value::String(signature.to_owned())).unwrap();
+
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        let signer = web3::signing::recover(
+                            &personal_message_hash(&body),
+                            &signature.0[..64],
+                            signature.0[64] as i32 - 27,
+                        )
+                        .unwrap();
+
+                        let call: jsonrpc_core::MethodCall = serde_json::from_slice(&body).unwrap();
+                        seen.lock().unwrap().push((signer, call.method.clone()));
+
+                        let result = match call.method.as_str() {
+                            "eth_callBundle" => serde_json::json!({
+                                "bundleGasPrice": "2000000000",
+                                "coinbaseDiff": "42000000000000",
+                                "totalGasUsed": 21000,
+                                "results": [{
+                                    "txHash": H256::from_low_u64_be(1),
+                                    "gasUsed": 21000,
+                                    "revert": "Payback failed",
+                                }],
+                            }),
+                            _ => serde_json::json!({ "bundleHash": H256::from_low_u64_be(2) }),
+                        };
+
+                        let response = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": call.id,
+                            "result": result,
+                        });
+                        Ok::<_, Infallible>(Response::new(Body::from(response.to_string())))
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn simulates_and_sends_signed_bundles() {
+        let key = PrivateKey::from_raw([1; 32]).unwrap();
+        let address = key.public_address();
+        let (url, requests) = stand_in_relay();
+        let relay = Relay::new(url, key);
+        let txs = vec![Bytes(vec![1, 2, 3])];
+
+        let simulation = relay.simulate(&txs, 10.into()).await.unwrap();
+        assert_eq!(simulation.coinbase_diff, U256::from(42_000_000_000_000u64));
+        assert_eq!(simulation.results[0].gas_used, 21000);
+        assert_eq!(
+            simulation.results[0].revert.as_deref(),
+            Some("Payback failed")
+        );
+
+        let hash = relay.send(&txs, 10.into()).await.unwrap();
+        assert_eq!(hash, H256::from_low_u64_be(2));
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                (address, "eth_callBundle".to_owned()),
+                (address, "eth_sendBundle".to_owned())
+            ]
+        );
+    }
+}
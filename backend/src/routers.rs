@@ -0,0 +1,88 @@
+use crate::gen::UniswapRouter;
+use ethcontract::common::abi::{Contract, ParamType};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+use web3::types::H160;
+
+/// Placeholder aggregators take in place of a token address for ether.
+pub const NATIVE_PLACEHOLDER: &str = "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee";
+
+lazy_static! {
+    static ref ONE_INCH_ABI: Contract =
+        Contract::load(&include_bytes!("../abis/routers/OneInchRouter.json")[..])
+            .expect("failed loading 1inch router abi");
+    static ref ZERO_EX_ABI: Contract =
+        Contract::load(&include_bytes!("../abis/routers/ZeroExProxy.json")[..])
+            .expect("failed loading 0x exchange proxy abi");
+    static ref BALANCER_EXCHANGE_PROXY_ABI: Contract = {
+        let mut abi =
+            Contract::load(&include_bytes!("../abis/routers/BalancerExchangeProxy.json")[..])
+                .expect("failed loading balancer exchange proxy abi");
+
+        // ethabi drops the components of nested tuple arrays, so the swap
+        // sequences are rebuilt from the single batch swaps.
+        let swaps = abi.functions["batchSwapExactIn"][0].inputs[0].kind.clone();
+        for name in &["multihopBatchSwapExactIn", "multihopBatchSwapExactOut"] {
+            for function in abi.functions.get_mut(*name).into_iter().flatten() {
+                function.inputs[0].kind = ParamType::Array(Box::new(swaps.clone()));
+            }
+        }
+
+        abi
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Protocol {
+    Uniswap,
+    Sushiswap,
+    OneInch,
+    ZeroEx,
+    BalancerExchangeProxy,
+}
+
+impl Protocol {
+    /// Functions of the protocol's router, as far as decoding its calldata goes.
+    pub fn abi(self) -> &'static Contract {
+        match self {
+            Protocol::Uniswap | Protocol::Sushiswap => &UniswapRouter::artifact().abi,
+            Protocol::OneInch => &ONE_INCH_ABI,
+            Protocol::ZeroEx => &ZERO_EX_ABI,
+            Protocol::BalancerExchangeProxy => &BALANCER_EXCHANGE_PROXY_ABI,
+        }
+    }
+}
+
+/// Router contracts whose calls are decoded as swaps, by protocol.
+#[derive(Clone, Debug)]
+pub struct Routers {
+    pub addresses: HashMap<H160, Protocol>,
+    /// Routers and aggregators whose calls aren't decoded.
+    pub aggregators: HashSet<H160>,
+    /// Token the aggregators' ether placeholder is read as.
+    pub wrapped_native: H160,
+}
+
+impl Routers {
+    pub fn protocol(&self, address: H160) -> Option<Protocol> {
+        self.addresses.get(&address).copied()
+    }
+
+    /// Whether the address is a router or aggregator, decoded or not.
+    pub fn known(&self, address: H160) -> bool {
+        self.addresses.contains_key(&address) || self.aggregators.contains(&address)
+    }
+
+    /// Reads the aggregators' ether placeholder as the wrapped native token.
+    pub fn token(&self, address: H160) -> H160 {
+        if address == H160::from_str(NATIVE_PLACEHOLDER).unwrap() {
+            self.wrapped_native
+        } else {
+            address
+        }
+    }
+}
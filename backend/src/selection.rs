@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use web3::types::{H160, U256};
+
+/// Most candidates searched exhaustively, the rest are only added greedily.
+const MAX_SEARCHED: usize = 16;
+
+/// An opportunity by the Uniswap pair and Balancer pool it trades on, and
+/// the profit it's expected to make.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    pub uniswap_pair: H160,
+    pub balancer_pool: H160,
+    pub profit: U256,
+}
+
+struct Search<'a> {
+    candidates: &'a [Candidate],
+    order: Vec<usize>,
+    /// Profit of the searched candidates from each position onwards.
+    remaining: Vec<U256>,
+    pairs: HashSet<H160>,
+    pools: HashSet<H160>,
+    chosen: Vec<usize>,
+    best: (U256, Vec<usize>),
+}
+
+impl Search<'_> {
+    fn run(&mut self, position: usize, profit: U256) {
+        if profit > self.best.0 {
+            self.best = (profit, self.chosen.clone());
+        }
+
+        if position == self.remaining.len() - 1
+            || profit.saturating_add(self.remaining[position]) <= self.best.0
+        {
+            return;
+        }
+
+        let index = self.order[position];
+        let candidate = self.candidates[index];
+
+        if !self.pairs.contains(&candidate.uniswap_pair)
+            && !self.pools.contains(&candidate.balancer_pool)
+        {
+            self.pairs.insert(candidate.uniswap_pair);
+            self.pools.insert(candidate.balancer_pool);
+            self.chosen.push(index);
+
+            self.run(position + 1, profit.saturating_add(candidate.profit));
+
+            self.chosen.pop();
+            self.pools.remove(&candidate.balancer_pool);
+            self.pairs.remove(&candidate.uniswap_pair);
+        }
+
+        self.run(position + 1, profit);
+    }
+}
+
+/// Indices of the candidates making the most profit together without any two
+/// of them trading on the same pair or pool, most profitable first. The ones
+/// without profit are never selected.
+pub fn select_disjoint(candidates: &[Candidate]) -> Vec<usize> {
+    let mut order: Vec<_> = (0..candidates.len())
+        .filter(|&index| !candidates[index].profit.is_zero())
+        .collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(candidates[index].profit));
+
+    let searched = order.len().min(MAX_SEARCHED);
+    let mut remaining = vec![U256::zero(); searched + 1];
+    for position in (0..searched).rev() {
+        remaining[position] =
+            remaining[position + 1].saturating_add(candidates[order[position]].profit);
+    }
+
+    let mut search = Search {
+        candidates,
+        order,
+        remaining,
+        pairs: HashSet::new(),
+        pools: HashSet::new(),
+        chosen: vec![],
+        best: (U256::zero(), vec![]),
+    };
+    search.run(0, U256::zero());
+
+    let mut chosen = search.best.1;
+    let mut pairs: HashSet<_> = chosen
+        .iter()
+        .map(|&index| candidates[index].uniswap_pair)
+        .collect();
+    let mut pools: HashSet<_> = chosen
+        .iter()
+        .map(|&index| candidates[index].balancer_pool)
+        .collect();
+
+    for &index in &search.order[searched..] {
+        let candidate = candidates[index];
+        if pairs.insert(candidate.uniswap_pair) {
+            if pools.insert(candidate.balancer_pool) {
+                chosen.push(index);
+            } else {
+                pairs.remove(&candidate.uniswap_pair);
+            }
+        }
+    }
+
+    chosen.sort_by_key(|&index| std::cmp::Reverse(candidates[index].profit));
+    chosen
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn beats_picking_the_best_first() {
+        let candidate = |pair, pool, profit: u64| Candidate {
+            uniswap_pair: H160::from_low_u64_be(pair),
+            balancer_pool: H160::from_low_u64_be(pool),
+            profit: profit.into(),
+        };
+
+        let candidates = vec![
+            candidate(1, 10, 10),
+            candidate(1, 11, 8),
+            candidate(2, 10, 7),
+            candidate(3, 12, 1),
+            candidate(3, 12, 2),
+            candidate(4, 13, 0),
+        ];
+        assert_eq!(select_disjoint(&candidates), vec![1, 2, 4]);
+
+        let many: Vec<_> = (0..40).map(|i| candidate(i, 100 + i, 1)).collect();
+        assert_eq!(select_disjoint(&many).len(), 40);
+        assert!(select_disjoint(&[]).is_empty());
+    }
+}
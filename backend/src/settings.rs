@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::{error::Error, net::SocketAddr, str::FromStr};
+use web3::types::H160;
+
+const FILE_PATH: &str = "watch_pairs.toml";
+const ENV_PREFIX: &str = "ARBRITO_";
+
+/// What watch_pairs runs with, read from the config file and then overridden
+/// by `ARBRITO_<KEY>` environment variables and `--<key> <value>` arguments.
+/// Keys are the field names, dashed on the command line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Name of the network profile to run on.
+    pub network: String,
+    /// Overrides the network's wrapped native token.
+    pub weth_address: Option<H160>,
+    /// Overrides the network's Arbrito deployment.
+    pub arbrito_address: Option<H160>,
+    /// Account dry runs simulate with, when no keys are given.
+    pub executor_address: H160,
+    /// Encrypted JSON keystores holding executor keys, decrypted with the
+    /// `ARBRITO_EXEC_PASSWORD` environment variable. Comma separated when
+    /// given as a string.
+    pub keystore_paths: Vec<String>,
+    /// Files holding executor keys hex encoded, comma separated likewise.
+    pub key_paths: Vec<String>,
+    pub expected_gas_usage: u64,
+    pub max_gas_usage: u64,
+    pub min_gas_scale: u8,
+    pub max_gas_scale: u8,
+    /// How executions bid for gas: `scaled` between the gas scales,
+    /// `profit_share` or `percentile` of recent blocks.
+    pub gas_strategy: String,
+    pub gas_profit_share_percent: u8,
+    pub gas_percentile: u8,
+    /// Checks attempts against the following block instead of executing them.
+    pub dry_run: bool,
+    /// Executes right behind pending swaps that move a pair or pool enough.
+    pub backrun: bool,
+    /// Least change, in basis points, a pending swap must make to a reserve
+    /// or balance to be backrun.
+    pub backrun_min_impact_bps: u64,
+    /// Sends executions as bundles to this relay instead of broadcasting them.
+    pub relay_url: Option<String>,
+    /// Share of the expected profit bundles bid to the miner, as a gas price
+    /// capped by the max one of the gas strategy, since the contract can't pay
+    /// the coinbase.
+    pub relay_bid_percent: u8,
+    /// Keeps the outcome of every execution in this file, one json per line,
+    /// summing up the profits and losses from it on start.
+    pub ledger_path: Option<String>,
+    /// Journals every attempt made in this SQLite database.
+    pub journal_path: Option<String>,
+    /// Serves Prometheus metrics at `/metrics` on this address.
+    pub metrics_address: Option<SocketAddr>,
+    /// Serves the admin api on this address, which has to be a loopback one
+    /// as requests aren't authenticated.
+    pub admin_address: Option<SocketAddr>,
+    /// Draws a live dashboard in the terminal instead of scrolling through
+    /// the logs, which still go to stderr and are best redirected.
+    pub dashboard: bool,
+    /// Appends every head and pending transaction seen to this file, along
+    /// with the state cache, so that the run can be replayed.
+    pub record_path: Option<String>,
+    /// Replays a recording instead of following the node, which is then only
+    /// read from on start.
+    pub replay_path: Option<String>,
+    /// How many times faster than recorded replays go, `inf` for as fast as
+    /// they are processed.
+    pub replay_speed: f64,
+}
+
+const KEYS: [&str; 26] = [
+    "network",
+    "weth_address",
+    "arbrito_address",
+    "executor_address",
+    "keystore_paths",
+    "key_paths",
+    "expected_gas_usage",
+    "max_gas_usage",
+    "min_gas_scale",
+    "max_gas_scale",
+    "gas_strategy",
+    "gas_profit_share_percent",
+    "gas_percentile",
+    "dry_run",
+    "backrun",
+    "backrun_min_impact_bps",
+    "relay_url",
+    "relay_bid_percent",
+    "ledger_path",
+    "journal_path",
+    "metrics_address",
+    "admin_address",
+    "dashboard",
+    "record_path",
+    "replay_path",
+    "replay_speed",
+];
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            network: "mainnet".to_owned(),
+            weth_address: None,
+            arbrito_address: None,
+            executor_address: H160::from_str("Af43007aD675D6C72E96905cf4d8acB58ba0E041").unwrap(),
+            keystore_paths: vec![],
+            key_paths: vec![],
+            expected_gas_usage: 350_000,
+            max_gas_usage: 400_000,
+            min_gas_scale: 2,
+            max_gas_scale: 5,
+            gas_strategy: "scaled".to_owned(),
+            gas_profit_share_percent: 50,
+            gas_percentile: 60,
+            dry_run: false,
+            backrun: false,
+            backrun_min_impact_bps: 30,
+            relay_url: None,
+            relay_bid_percent: 90,
+            ledger_path: None,
+            journal_path: None,
+            metrics_address: None,
+            admin_address: None,
+            dashboard: false,
+            record_path: None,
+            replay_path: None,
+            replay_speed: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the file named by `--config`, or the default
+    /// one when it exists, then applies the environment and the arguments.
+    pub fn load(args: impl Iterator<Item = String>) -> Result<Settings, Box<dyn Error>> {
+        let args = parse_args(args)?;
+
+        let mut settings = match args.iter().find(|(key, _)| key == "config") {
+            Some((_, path)) => Settings::read(path)?,
+            None if std::path::Path::new(FILE_PATH).exists() => Settings::read(FILE_PATH)?,
+            None => Settings::default(),
+        };
+
+        for key in &KEYS {
+            let var = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                settings.set(key, &value)?;
+            }
+        }
+
+        for (key, value) in args.iter().filter(|(key, _)| key != "config") {
+            settings.set(key, value)?;
+        }
+
+        Ok(settings)
+    }
+
+    pub fn read(path: &str) -> Result<Settings, Box<dyn Error>> {
+        let bytes = std::fs::read(path)?;
+        let settings: Settings = toml::from_slice(&bytes)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Sets the value of the given key, leaving the settings as they are when
+    /// it doesn't parse or isn't valid.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let mut settings = self.clone();
+        match key {
+            "network" => settings.network = value.to_owned(),
+            "weth_address" => settings.weth_address = Some(H160::from_str(value)?),
+            "arbrito_address" => settings.arbrito_address = Some(H160::from_str(value)?),
+            "executor_address" => settings.executor_address = H160::from_str(value)?,
+            "keystore_paths" => settings.keystore_paths = split_list(value),
+            "key_paths" => settings.key_paths = split_list(value),
+            "expected_gas_usage" => settings.expected_gas_usage = value.parse()?,
+            "max_gas_usage" => settings.max_gas_usage = value.parse()?,
+            "min_gas_scale" => settings.min_gas_scale = value.parse()?,
+            "max_gas_scale" => settings.max_gas_scale = value.parse()?,
+            "gas_strategy" => settings.gas_strategy = value.to_owned(),
+            "gas_profit_share_percent" => settings.gas_profit_share_percent = value.parse()?,
+            "gas_percentile" => settings.gas_percentile = value.parse()?,
+            "dry_run" => settings.dry_run = value.parse()?,
+            "backrun" => settings.backrun = value.parse()?,
+            "backrun_min_impact_bps" => settings.backrun_min_impact_bps = value.parse()?,
+            "relay_url" => settings.relay_url = Some(value.to_owned()),
+            "relay_bid_percent" => settings.relay_bid_percent = value.parse()?,
+            "ledger_path" => settings.ledger_path = Some(value.to_owned()),
+            "journal_path" => settings.journal_path = Some(value.to_owned()),
+            "metrics_address" => settings.metrics_address = Some(value.parse()?),
+            "admin_address" => settings.admin_address = Some(value.parse()?),
+            "dashboard" => settings.dashboard = value.parse()?,
+            "record_path" => settings.record_path = Some(value.to_owned()),
+            "replay_path" => settings.replay_path = Some(value.to_owned()),
+            "replay_speed" => settings.replay_speed = value.parse()?,
+            _ => return Err(format!("unknown setting {}", key).into()),
+        }
+
+        settings.validate()?;
+        *self = settings;
+        Ok(())
+    }
+
+    /// Checks what parsing the values doesn't, wherever they were read from.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        match self.gas_strategy.as_str() {
+            "scaled" | "profit_share" | "percentile" => {}
+            strategy => return Err(format!("unknown gas strategy {}", strategy).into()),
+        }
+
+        let percents = [
+            ("gas_profit_share_percent", self.gas_profit_share_percent),
+            ("gas_percentile", self.gas_percentile),
+            ("relay_bid_percent", self.relay_bid_percent),
+        ];
+        for &(key, percent) in &percents {
+            if percent > 100 {
+                return Err(format!("{} over 100: {}", key, percent).into());
+            }
+        }
+
+        if self.replay_speed.is_nan() || self.replay_speed <= 0.0 {
+            return Err(format!("replay speed not positive: {}", self.replay_speed).into());
+        }
+
+        match self.admin_address {
+            Some(address) if !address.ip().is_loopback() => {
+                Err(format!("admin address not a loopback one: {}", address).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Pairs up `--key value` and `--key=value` arguments, with keys underscored.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Vec<(String, String)>, String> {
+    let mut pairs = vec![];
+
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .strip_prefix("--")
+            .ok_or_else(|| format!("unexpected argument {}", arg))?
+            .replace('-', "_");
+
+        let (key, value) = match arg.find('=') {
+            Some(index) => (arg[..index].to_owned(), arg[index + 1..].to_owned()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for {}", arg))?;
+                (arg, value)
+            }
+        };
+
+        pairs.push((key, value));
+    }
+
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn overrides_file_with_arguments() {
+        let mut settings: Settings = toml::from_str(
+            r#"
+            executor_address = "0x0000000000000000000000000000000000000001"
+            min_gas_scale = 3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.executor_address, H160::from_low_u64_be(1));
+        assert_eq!(settings.min_gas_scale, 3);
+        assert_eq!(settings.max_gas_scale, Settings::default().max_gas_scale);
+
+        let args = vec![
+            "--min-gas-scale",
+            "4",
+            "--max-gas-usage=500000",
+            "--network=kovan",
+            "--dry-run=true",
+            "--relay-url",
+            "http://localhost:8545",
+            "--key-paths",
+            "a.key, b.key",
+        ];
+        for (key, value) in parse_args(args.into_iter().map(String::from)).unwrap() {
+            settings.set(&key, &value).unwrap();
+        }
+        assert_eq!(settings.min_gas_scale, 4);
+        assert_eq!(settings.max_gas_usage, 500_000);
+        assert_eq!(settings.network, "kovan");
+        assert!(settings.dry_run);
+        assert_eq!(settings.weth_address, None);
+        assert_eq!(settings.relay_url.as_deref(), Some("http://localhost:8545"));
+        assert!(settings.set("relay_bid_percent", "101").is_err());
+        assert!(settings.set("gas_strategy", "percentile").is_ok());
+        assert!(settings.set("gas_strategy", "yolo").is_err());
+        assert_eq!(settings.key_paths, vec!["a.key", "b.key"]);
+        assert!(settings.set("metrics_address", "127.0.0.1:9100").is_ok());
+        assert!(settings.set("metrics_address", "localhost").is_err());
+        assert!(settings.set("admin_address", "127.0.0.1:9200").is_ok());
+        assert!(settings.set("admin_address", "0.0.0.0:9200").is_err());
+        assert!(settings.set("dashboard", "yes").is_err());
+        assert!(settings.set("replay_speed", "inf").is_ok());
+        assert!(settings.set("replay_speed", "0").is_err());
+
+        assert!(settings.set("min_gas_scale", "lots").is_err());
+        assert!(settings.set("max_gas", "1").is_err());
+        assert!(parse_args(vec!["--min-gas-scale".to_owned()].into_iter()).is_err());
+
+        let file: Settings = toml::from_str(include_str!("../watch_pairs.toml")).unwrap();
+        assert_eq!(file, Settings::default());
+    }
+
+    #[test]
+    fn validates_files_like_arguments() {
+        let path = std::env::temp_dir().join(format!("settings-{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let invalid = [
+            "gas_strategy = \"yolo\"",
+            "gas_profit_share_percent = 101",
+            "gas_percentile = 255",
+            "relay_bid_percent = 101",
+            "replay_speed = 0.0",
+            "replay_speed = -1.0",
+            "replay_speed = nan",
+            "admin_address = \"0.0.0.0:9200\"",
+        ];
+        for contents in invalid.iter().chain(&["relay_bid_percent = 100"]) {
+            std::fs::write(path, contents).unwrap();
+            let settings = Settings::read(path);
+            assert_eq!(
+                settings.is_ok(),
+                !invalid.contains(contents),
+                "{}",
+                contents
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+
+        let mut settings = Settings::default();
+        assert!(settings.set("replay_speed", "nan").is_err());
+        assert!(settings.set("gas_percentile", "101").is_err());
+        assert_eq!(settings, Settings::default());
+    }
+}
@@ -0,0 +1,98 @@
+use ethcontract::{Account, PrivateKey};
+use std::error::Error;
+use web3::types::H160;
+
+/// Signs executions locally, so that they can be sent raw and the node never
+/// holds the executor's key.
+#[derive(Debug, Clone)]
+pub struct Signer {
+    key: PrivateKey,
+    chain_id: u64,
+}
+
+impl Signer {
+    pub fn new(key: PrivateKey, chain_id: u64) -> Signer {
+        Signer { key, chain_id }
+    }
+
+    /// Decrypts the key from a JSON keystore file.
+    pub fn from_keystore(
+        path: &str,
+        password: &str,
+        chain_id: u64,
+    ) -> Result<Signer, Box<dyn Error>> {
+        let key = eth_keystore::decrypt_key(path, password)?;
+        Ok(Signer::new(PrivateKey::from_slice(key)?, chain_id))
+    }
+
+    /// Reads the key from a file holding it hex encoded.
+    pub fn from_key_file(path: &str, chain_id: u64) -> Result<Signer, Box<dyn Error>> {
+        let key = std::fs::read_to_string(path)?;
+        Ok(Signer::new(PrivateKey::from_hex_str(key.trim())?, chain_id))
+    }
+
+    pub fn address(&self) -> H160 {
+        self.key.public_address()
+    }
+
+    pub fn key(&self) -> &PrivateKey {
+        &self.key
+    }
+
+    /// The account to build transactions with, signed for the chain.
+    pub fn account(&self) -> Account {
+        Account::Offline(self.key.clone(), Some(self.chain_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    const KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    const KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    #[test]
+    fn loads_keystores_and_key_files() {
+        let dir = std::env::temp_dir();
+        let keystore_path = dir.join(format!("keystore-{}.json", std::process::id()));
+        let keystore_path = keystore_path.to_str().unwrap();
+        let key_path = dir.join(format!("key-{}", std::process::id()));
+        let key_path = key_path.to_str().unwrap();
+
+        std::fs::write(keystore_path, KEYSTORE).unwrap();
+        std::fs::write(key_path, format!("0x{}\n", KEY)).unwrap();
+
+        let keystore = Signer::from_keystore(keystore_path, "testpassword", 1);
+        let wrong_password = Signer::from_keystore(keystore_path, "wrongpassword", 1);
+        let key_file = Signer::from_key_file(key_path, 1);
+        std::fs::remove_file(keystore_path).unwrap();
+        std::fs::remove_file(key_path).unwrap();
+
+        let address = PrivateKey::from_str(KEY).unwrap().public_address();
+        assert_eq!(keystore.unwrap().address(), address);
+        assert!(wrong_password.is_err());
+
+        let key_file = key_file.unwrap();
+        assert_eq!(key_file.address(), address);
+        assert!(matches!(key_file.account(), Account::Offline(_, Some(1))));
+    }
+}
@@ -0,0 +1,498 @@
+use crate::{
+    gen::{balancer_pool, uniswap_pair, BalancerPool, UniswapPair},
+    latest_block::Head,
+    metadata::{BalancerPoolMetadata, UniswapPairMetadata},
+};
+use ethcontract::{contract::ParseLog, errors::MethodError, BlockId, BlockNumber, RawLog};
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use web3::{
+    futures::future::try_join_all,
+    types::{Log, H160, H256, U256, U64},
+};
+
+/// Pool calls that may change its swap fee, weights or finalization, or its
+/// balances without a log telling by how much.
+const STALE_CALLS: [[u8; 4]; 7] = [
+    [0x34, 0xe1, 0x99, 0x07], // setSwapFee
+    [0x4b, 0xb2, 0x78, 0xf3], // finalize
+    [0x49, 0xb5, 0x95, 0x52], // setPublicSwap
+    [0xe4, 0xe1, 0xe5, 0x38], // bind
+    [0x3f, 0xdd, 0xda, 0xa2], // rebind
+    [0xcf, 0x5e, 0x7b, 0xd3], // unbind
+    [0x8c, 0x28, 0xcb, 0xe8], // gulp
+];
+
+pub struct UniswapPairBase {
+    pub contract: UniswapPair,
+    pub address: H160,
+    pub metadata: UniswapPairMetadata,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UniswapPairResolved {
+    pub reserve0: U256,
+    pub reserve1: U256,
+    pub token0: H160,
+}
+
+pub struct BalancerPoolBase {
+    pub contract: BalancerPool,
+    pub tokens: HashSet<H160>,
+    pub address: H160,
+    pub metadata: BalancerPoolMetadata,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalancerPoolResolved {
+    pub balances: HashMap<H160, U256>,
+    pub swap_fee: U256,
+}
+
+impl UniswapPairResolved {
+    /// Reserves as (in, out) for a swap selling `token_in`.
+    pub fn reserves(&self, token_in: H160) -> (U256, U256) {
+        if self.token0 == token_in {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        }
+    }
+
+    /// Applies the swap, unless it would overflow or underflow a reserve, as
+    /// swaps decoded against stale state may, in which case nothing changes.
+    pub fn swap(&mut self, token_in: H160, amount_in: U256, amount_out: U256) -> Option<()> {
+        let (reserve_in, reserve_out) = self.reserves(token_in);
+        let reserve_in = reserve_in.checked_add(amount_in)?;
+        let reserve_out = reserve_out.checked_sub(amount_out)?;
+
+        if self.token0 == token_in {
+            self.reserve0 = reserve_in;
+            self.reserve1 = reserve_out;
+        } else {
+            self.reserve1 = reserve_in;
+            self.reserve0 = reserve_out;
+        }
+        Some(())
+    }
+
+    pub fn deposit(&mut self, token: H160, amount: U256) -> Option<()> {
+        self.swap(token, amount, U256::zero())
+    }
+}
+
+impl BalancerPoolResolved {
+    /// Applies the swap to the balances of the tokens the pool is tracked
+    /// with, unless it would overflow or underflow one, in which case nothing
+    /// changes.
+    pub fn swap(
+        &mut self,
+        token_in: H160,
+        amount_in: U256,
+        token_out: H160,
+        amount_out: U256,
+    ) -> Option<()> {
+        let balance_in = match self.balances.get(&token_in) {
+            Some(balance) => Some(balance.checked_add(amount_in)?),
+            None => None,
+        };
+        let balance_out = match self.balances.get(&token_out) {
+            Some(balance) => Some(balance.checked_sub(amount_out)?),
+            None => None,
+        };
+
+        if let Some(balance) = balance_in {
+            self.balances.insert(token_in, balance);
+        }
+        if let Some(balance) = balance_out {
+            self.balances.insert(token_out, balance);
+        }
+        Some(())
+    }
+}
+
+impl UniswapPairBase {
+    pub async fn resolve(
+        &self,
+        block: BlockId,
+    ) -> Result<(H160, UniswapPairResolved), MethodError> {
+        let req = self.contract.get_reserves().block(block);
+        let (reserve0, reserve1, _) = req.call().await?;
+
+        Ok((
+            self.address,
+            UniswapPairResolved {
+                reserve0: U256::from(reserve0),
+                reserve1: U256::from(reserve1),
+                token0: self.metadata.token0,
+            },
+        ))
+    }
+}
+
+impl BalancerPoolBase {
+    pub async fn resolve(
+        &self,
+        block: BlockId,
+    ) -> Result<(H160, BalancerPoolResolved), MethodError> {
+        let futs = self.tokens.iter().copied().map(|t| {
+            let req = self.contract.get_balance(t).block(block);
+            req.call().map(move |r| r.map(|balance| (t, balance)))
+        });
+
+        let balances = try_join_all(futs).await?.into_iter().collect();
+        let swap_fee = self.metadata.swap_fee;
+        Ok((self.address, BalancerPoolResolved { balances, swap_fee }))
+    }
+
+    pub async fn refresh(&mut self, block: BlockId) -> Result<(), MethodError> {
+        self.metadata = BalancerPoolMetadata::fetch(&self.contract, &self.tokens, block).await?;
+        Ok(())
+    }
+}
+
+/// Keeps the resolved state of every pair and pool up to date by replaying
+/// their logs on top of a single full snapshot, instead of re-reading them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateCache {
+    pub pairs: HashMap<H160, UniswapPairResolved>,
+    pub pools: HashMap<H160, BalancerPoolResolved>,
+    pub number: U64,
+    /// Hash of the cached block, zero when unknown, in which case the next
+    /// head is taken as following it.
+    pub hash: H256,
+    /// Pools called into in ways their logs don't account for, to be resolved
+    /// again.
+    pub stale_pools: HashSet<H160>,
+}
+
+impl StateCache {
+    pub async fn snapshot(
+        uniswap_pair_bases: &HashMap<H160, UniswapPairBase>,
+        balancer_pool_bases: &HashMap<H160, BalancerPoolBase>,
+        number: U64,
+    ) -> Result<StateCache, MethodError> {
+        let block = BlockId::Number(BlockNumber::Number(number));
+
+        let futs = uniswap_pair_bases.values().map(|pair| pair.resolve(block));
+        let pairs = try_join_all(futs).await?.into_iter().collect();
+
+        let futs = balancer_pool_bases.values().map(|pool| pool.resolve(block));
+        let pools = try_join_all(futs).await?.into_iter().collect();
+
+        Ok(StateCache {
+            stale_pools: HashSet::new(),
+            hash: H256::zero(),
+            pairs,
+            pools,
+            number,
+        })
+    }
+
+    /// Every pair and pool whose logs the cache is kept up to date with.
+    pub fn markets(&self) -> Vec<H160> {
+        self.pairs
+            .keys()
+            .chain(self.pools.keys())
+            .copied()
+            .collect()
+    }
+
+    /// Applies the logs of every block after the cached one up to the head,
+    /// unless the cache is already past it. Returns false, leaving the cache
+    /// as it is, when the head isn't on the chain of the cached block, as
+    /// after a reorg, in which case it has to be snapshot again.
+    pub fn update(&mut self, head: &Head) -> bool {
+        if head.hash == self.hash {
+            return true;
+        }
+        if !self.hash.is_zero() && head.parent_hash != self.hash {
+            return false;
+        }
+
+        if head.number > self.number {
+            for log in head.logs.iter().cloned() {
+                self.apply_log(log);
+            }
+
+            self.number = head.number;
+            self.hash = head.hash;
+        }
+        true
+    }
+
+    pub fn apply_log(&mut self, log: Log) {
+        if log.is_removed() {
+            return;
+        }
+
+        let address = log.address;
+
+        if let Some(pair) = self.pairs.get_mut(&address) {
+            if let Ok(uniswap_pair::Event::Sync(sync)) =
+                uniswap_pair::Event::parse_log(RawLog::from(log))
+            {
+                pair.reserve0 = U256::from(sync.reserve_0);
+                pair.reserve1 = U256::from(sync.reserve_1);
+            }
+        } else if let Some(pool) = self.pools.get_mut(&address) {
+            let mut shift = |token, amount, add: bool| {
+                if let Some(balance) = pool.balances.get_mut(&token) {
+                    *balance = if add {
+                        balance.saturating_add(amount)
+                    } else {
+                        balance.saturating_sub(amount)
+                    };
+                }
+            };
+
+            match balancer_pool::Event::parse_log(RawLog::from(log)) {
+                Ok(balancer_pool::Event::LogSwap(swap)) => {
+                    shift(swap.token_in, swap.token_amount_in, true);
+                    shift(swap.token_out, swap.token_amount_out, false);
+                }
+                Ok(balancer_pool::Event::LogJoin(join)) => {
+                    shift(join.token_in, join.token_amount_in, true);
+                }
+                Ok(balancer_pool::Event::LogExit(exit)) => {
+                    shift(exit.token_out, exit.token_amount_out, false);
+                }
+                Ok(balancer_pool::Event::LogCall(call)) if STALE_CALLS.contains(&call.sig) => {
+                    self.stale_pools.insert(address);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Compares the cache against a fresh on-chain snapshot at the cached block,
+    /// replacing every drifted entry. Returns how many entries had drifted.
+    /// Pools whose swap fee drifted are marked stale, as their cached metadata
+    /// is too.
+    pub async fn checksum(
+        &mut self,
+        uniswap_pair_bases: &HashMap<H160, UniswapPairBase>,
+        balancer_pool_bases: &HashMap<H160, BalancerPoolBase>,
+    ) -> Result<usize, MethodError> {
+        let block = BlockId::Number(BlockNumber::Number(self.number));
+        let (fresh, swap_fees) = futures::join!(
+            StateCache::snapshot(uniswap_pair_bases, balancer_pool_bases, self.number),
+            try_join_all(balancer_pool_bases.values().map(|pool| {
+                let req = pool.contract.get_swap_fee().block(block);
+                req.call()
+                    .map(move |r| r.map(|swap_fee| (pool.address, swap_fee)))
+            })),
+        );
+        let (mut fresh, swap_fees) = (fresh?, swap_fees?);
+        let mut drifts = 0;
+
+        for (address, swap_fee) in swap_fees {
+            if let Some(pool) = fresh.pools.get_mut(&address) {
+                pool.swap_fee = swap_fee;
+            }
+
+            if self.pools.get(&address).map(|pool| pool.swap_fee) != Some(swap_fee) {
+                self.stale_pools.insert(address);
+            }
+        }
+
+        for (address, pair) in fresh.pairs {
+            if self.pairs.get(&address) != Some(&pair) {
+                log::warn!("state cache drift on uniswap pair {:?}", address);
+                self.pairs.insert(address, pair);
+                drifts += 1;
+            }
+        }
+
+        for (address, pool) in fresh.pools {
+            if self.pools.get(&address) != Some(&pool) {
+                log::warn!("state cache drift on balancer pool {:?}", address);
+                self.pools.insert(address, pool);
+                drifts += 1;
+            }
+        }
+
+        Ok(drifts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use web3::{
+        signing::keccak256,
+        types::{Bytes, H256},
+    };
+
+    fn log(address: H160, topics: Vec<H256>, data: Vec<U256>) -> Log {
+        let mut bytes = vec![0; 32 * data.len()];
+        for (chunk, value) in bytes.chunks_exact_mut(32).zip(data) {
+            value.to_big_endian(chunk);
+        }
+
+        Log {
+            address,
+            topics,
+            data: Bytes(bytes),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    fn topic(address: H160) -> H256 {
+        H256::from(address)
+    }
+
+    #[test]
+    fn applies_logs() {
+        let pair = H160::from_low_u64_be(1);
+        let pool = H160::from_low_u64_be(2);
+        let token0 = H160::from_low_u64_be(3);
+        let token1 = H160::from_low_u64_be(4);
+
+        let mut cache = StateCache {
+            pairs: vec![(
+                pair,
+                UniswapPairResolved {
+                    reserve0: U256::from(10),
+                    reserve1: U256::from(20),
+                    token0,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            pools: vec![(
+                pool,
+                BalancerPoolResolved {
+                    balances: vec![(token0, U256::from(100)), (token1, U256::from(200))]
+                        .into_iter()
+                        .collect(),
+                    swap_fee: U256::zero(),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            number: U64::zero(),
+            hash: H256::zero(),
+            stale_pools: HashSet::new(),
+        };
+
+        cache.apply_log(log(
+            pair,
+            vec![uniswap_pair::event_data::Sync::signature()],
+            vec![U256::from(11), U256::from(19)],
+        ));
+
+        cache.apply_log(log(
+            pool,
+            vec![
+                balancer_pool::event_data::LogSwap::signature(),
+                topic(H160::zero()),
+                topic(token0),
+                topic(token1),
+            ],
+            vec![U256::from(5), U256::from(8)],
+        ));
+
+        cache.apply_log(log(
+            pool,
+            vec![
+                balancer_pool::event_data::LogExit::signature(),
+                topic(H160::zero()),
+                topic(token0),
+            ],
+            vec![U256::from(50)],
+        ));
+
+        assert!(cache.stale_pools.is_empty());
+        let mut gulp = H256::zero();
+        gulp[..4].copy_from_slice(&STALE_CALLS[6]);
+        cache.apply_log(log(
+            pool,
+            vec![gulp, topic(H160::zero())],
+            vec![U256::from(32), U256::from(4), U256::from(gulp.as_bytes())],
+        ));
+        assert!(cache.stale_pools.contains(&pool));
+
+        let pair = &cache.pairs[&pair];
+        assert_eq!(pair.reserve0, U256::from(11));
+        assert_eq!(pair.reserve1, U256::from(19));
+
+        let pool = &cache.pools[&pool];
+        assert_eq!(pool.balances[&token0], U256::from(55));
+        assert_eq!(pool.balances[&token1], U256::from(192));
+    }
+
+    #[test]
+    fn follows_heads_of_the_cached_chain() {
+        let pair = H160::from_low_u64_be(1);
+        let mut cache = StateCache {
+            pairs: vec![(
+                pair,
+                UniswapPairResolved {
+                    reserve0: U256::from(10),
+                    reserve1: U256::from(20),
+                    token0: H160::zero(),
+                },
+            )]
+            .into_iter()
+            .collect(),
+            pools: HashMap::new(),
+            number: 1.into(),
+            hash: H256::from_low_u64_be(1),
+            stale_pools: HashSet::new(),
+        };
+
+        let head = |number: u64, hash, parent_hash, reserve0: u64| Head {
+            number: number.into(),
+            hash: H256::from_low_u64_be(hash),
+            parent_hash: H256::from_low_u64_be(parent_hash),
+            gas_price: U256::one(),
+            accounts: vec![],
+            logs: vec![log(
+                pair,
+                vec![uniswap_pair::event_data::Sync::signature()],
+                vec![U256::from(reserve0), U256::from(20)],
+            )],
+            transactions: vec![],
+        };
+
+        assert!(cache.update(&head(3, 3, 1, 11)));
+        assert_eq!(cache.pairs[&pair].reserve0, U256::from(11));
+        assert!(cache.update(&head(3, 3, 1, 12)));
+        assert_eq!(cache.pairs[&pair].reserve0, U256::from(11));
+
+        // Heads replacing the cached one at the same or a lower height.
+        assert!(!cache.update(&head(3, 4, 2, 13)));
+        assert!(!cache.update(&head(2, 5, 1, 13)));
+        assert_eq!(cache.pairs[&pair].reserve0, U256::from(11));
+        assert_eq!(
+            (cache.number, cache.hash),
+            (3.into(), H256::from_low_u64_be(3))
+        );
+    }
+
+    #[test]
+    fn stale_calls_are_pool_selectors() {
+        let signatures = [
+            "setSwapFee(uint256)",
+            "finalize()",
+            "setPublicSwap(bool)",
+            "bind(address,uint256,uint256)",
+            "rebind(address,uint256,uint256)",
+            "unbind(address)",
+            "gulp(address)",
+        ];
+
+        for (selector, signature) in STALE_CALLS.iter().zip(&signatures) {
+            assert_eq!(selector[..], keccak256(signature.as_bytes())[..4]);
+        }
+    }
+}